@@ -0,0 +1,16 @@
+use std::{env, fs, path::PathBuf};
+
+fn main() {
+    let out_dir = PathBuf::from(env::var("OUT_DIR").unwrap());
+    let symbols = out_dir.join("symbols.txt");
+
+    println!("cargo:rerun-if-env-changed=PANDA_SYMBOLS");
+
+    match env::var("PANDA_SYMBOLS") {
+        Ok(path) => {
+            println!("cargo:rerun-if-changed={}", path);
+            fs::copy(&path, &symbols).expect("Failed to copy kernel symbol table");
+        }
+        Err(_) => fs::write(&symbols, "").expect("Failed to write empty symbol table"),
+    }
+}
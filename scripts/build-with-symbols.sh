@@ -0,0 +1,25 @@
+#!/bin/sh
+# Builds the kernel with its own symbol table embedded, so backtraces are
+# printed with function names. Arguments are passed through to `cargo build`.
+#
+# Embedding the table moves code around, so the first table is stale. The
+# table's size doesn't change from the second build on, so the third build's
+# layout matches the table embedded in it.
+set -e
+
+profile=debug
+for arg in "$@"; do
+    if [ "$arg" = "--release" ]; then
+        profile=release
+    fi
+done
+
+kernel="target/x86_64-no-os/$profile/panda"
+symbols="$PWD/target/symbols.txt"
+
+cargo build "$@"
+
+for pass in 1 2; do
+    nm -n -C --defined-only "$kernel" > "$symbols"
+    PANDA_SYMBOLS="$symbols" cargo build "$@"
+done
@@ -1,8 +1,22 @@
+use alloc::collections::BTreeMap;
 use core::ptr::NonNull;
 
-use x86_64::PhysAddr;
+use lazy_static::lazy_static;
+use spin::Mutex;
+use x86_64::{instructions::interrupts, PhysAddr};
 
-use crate::memory;
+use crate::memory::{
+    self,
+    mmio::{CacheMode, MmioMapping},
+};
+
+const PAGE_SIZE: u64 = 4096;
+
+lazy_static! {
+    // Regions outside the physical memory mapping that have been handed to
+    // the acpi crate, by virtual address, until it gives them back.
+    static ref MAPPINGS: Mutex<BTreeMap<usize, MmioMapping>> = Mutex::new(BTreeMap::new());
+}
 
 #[derive(Debug, Copy, Clone)]
 pub struct AcpiMappingHandler;
@@ -13,10 +27,25 @@ impl acpi::AcpiHandler for AcpiMappingHandler {
         physical_address: usize,
         size: usize,
     ) -> acpi::PhysicalMapping<T> {
+        let physical_start = PhysAddr::new(physical_address as u64);
+
+        // Tables in RAM are already mapped write-back at the physical memory
+        // offset; mapping them again would alias them with another memory
+        // type. Only ones outside it, if any, get a mapping of their own.
+        let virtual_address = if is_offset_mapped(physical_start, size) {
+            memory::physical_to_virtual_address(physical_start).as_mut_ptr()
+        } else {
+            let mapping = memory::map_mmio(physical_start, size, CacheMode::WriteBack);
+            let virtual_address = mapping.as_mut_ptr::<T>();
+
+            interrupts::without_interrupts(|| {
+                MAPPINGS.lock().insert(virtual_address as usize, mapping)
+            });
+
+            virtual_address
+        };
         let virtual_address =
-            memory::physical_to_virtual_address(PhysAddr::new(physical_address as u64));
-        let virtual_address =
-            NonNull::new(virtual_address.as_mut_ptr()).expect("Could not map physical address");
+            NonNull::new(virtual_address).expect("Could not map physical address");
 
         acpi::PhysicalMapping {
             physical_start: physical_address,
@@ -26,7 +55,20 @@ impl acpi::AcpiHandler for AcpiMappingHandler {
         }
     }
 
-    fn unmap_physical_region<T>(&mut self, _region: acpi::PhysicalMapping<T>) {
-        // nothing to do
+    fn unmap_physical_region<T>(&mut self, region: acpi::PhysicalMapping<T>) {
+        let address = region.virtual_start.as_ptr() as usize;
+        let mapping = interrupts::without_interrupts(|| MAPPINGS.lock().remove(&address));
+
+        // Dropped outside the lock, as unmapping waits on the other CPUs.
+        drop(mapping);
     }
 }
+
+fn is_offset_mapped(physical_start: PhysAddr, size: usize) -> bool {
+    let start = physical_start.as_u64() & !(PAGE_SIZE - 1);
+    let end = physical_start.as_u64() + size.max(1) as u64;
+
+    (start..end)
+        .step_by(PAGE_SIZE as usize)
+        .all(|page| memory::is_mapped(memory::physical_to_virtual_address(PhysAddr::new(page))))
+}
@@ -5,7 +5,7 @@ mod mapping_handler;
 use core::{fmt::Display, hash::Hash};
 
 use crate::{acpi, pci};
-use ::acpi::{Acpi, PciConfigRegions};
+use ::acpi::{Acpi, HpetInfo, InterruptModel, PciConfigRegions, Processor};
 use alloc::boxed::Box;
 use alloc::vec::Vec;
 use context_handler::AmlContextHandler;
@@ -82,6 +82,23 @@ pub fn pci_config_regions() -> Option<&'static PciConfigRegions> {
     rsdp().pci_config_regions.as_ref()
 }
 
+pub fn interrupt_model() -> Option<&'static InterruptModel> {
+    rsdp().interrupt_model.as_ref()
+}
+
+pub fn hpet() -> Option<&'static HpetInfo> {
+    rsdp().hpet.as_ref()
+}
+
+pub fn boot_processor() -> Option<&'static Processor> {
+    rsdp().boot_processor.as_ref()
+}
+
+/// Every processor in the MADT other than the one we booted on.
+pub fn application_processors() -> &'static [Processor] {
+    &rsdp().application_processors
+}
+
 pub fn search(start: &AmlName, name: &str) -> Result<AmlName, AmlError> {
     let name = AmlName::from_str(name)?;
     let name = name.resolve(start)?;
@@ -126,21 +143,36 @@ pub fn pci_address_for_acpi_address(acpi_address: &AcpiDeviceAddress) -> Option<
 
     println!("Finding PCI address for ACPI device {}", aml_name.as_string());
 
-    let segment = acpi::search(aml_name, "_SEG")
-        .map(|segment| acpi::get_as_integer(&segment).expect("failed to get segment number"))
-        .unwrap_or(0) as u16;
-
-    let bus = acpi::search(aml_name, "_BBN")
-        .map(|bus| acpi::get_as_integer(&bus).expect("failed to get bus number"))
-        .unwrap_or(0) as u8;
+    let segment = match acpi::search(aml_name, "_SEG").map(|name| acpi::get_as_integer(&name)) {
+        Ok(Ok(segment)) => segment as u16,
+        Ok(Err(error)) => {
+            println!(" -> failed to get segment number: {:?}", error);
+            return None;
+        }
+        Err(_) => 0,
+    };
+
+    let bus = match acpi::search(aml_name, "_BBN").map(|name| acpi::get_as_integer(&name)) {
+        Ok(Ok(bus)) => bus as u8,
+        Ok(Err(error)) => {
+            println!(" -> failed to get bus number: {:?}", error);
+            return None;
+        }
+        Err(_) => 0,
+    };
 
     let adr =
         acpi::get_as_integer(&aml_name.child(&AmlName::from_str("_ADR").unwrap())).ok()? as u32;
     let slot = (adr >> 16) as u8;
     let function = (adr & 0xff) as u8;
 
-    let base_address = pci::base_address_for_segment(segment)
-        .expect("Could not find PCI base address");
+    let base_address = match pci::base_address_for_segment(segment) {
+        Some(base_address) => base_address,
+        None => {
+            println!(" -> no PCI base address for segment {}", segment);
+            return None;
+        }
+    };
 
     let pci_address = PciDeviceAddress::new(
         base_address,
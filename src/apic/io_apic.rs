@@ -0,0 +1,135 @@
+use bit_field::BitField;
+use x86_64::PhysAddr;
+
+use crate::memory::{self, mmio::CacheMode, mmio::MmioMapping};
+
+const IOREGSEL: usize = 0x00;
+const IOWIN: usize = 0x10;
+
+const IOAPICVER: u32 = 0x01;
+const IOREDTBL: u32 = 0x10;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Polarity {
+    ActiveHigh,
+    ActiveLow,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TriggerMode {
+    Edge,
+    Level,
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct RedirectionEntry {
+    pub vector: u8,
+    pub polarity: Polarity,
+    pub trigger_mode: TriggerMode,
+    pub masked: bool,
+    pub destination: u8,
+}
+
+impl RedirectionEntry {
+    fn to_bits(&self) -> u64 {
+        let mut bits = 0u64;
+        bits.set_bits(0..8, self.vector as u64);
+        // Delivery mode (bits 8-10) fixed and destination mode (bit 11)
+        // physical are both zero.
+        bits.set_bit(13, self.polarity == Polarity::ActiveLow);
+        bits.set_bit(15, self.trigger_mode == TriggerMode::Level);
+        bits.set_bit(16, self.masked);
+        bits.set_bits(56..64, self.destination as u64);
+        bits
+    }
+}
+
+/// One I/O APIC, handling the global system interrupts from
+/// `gsi_base` to `gsi_base + redirection_entries - 1`.
+pub struct IoApic {
+    id: u8,
+    gsi_base: u32,
+    redirection_entries: u32,
+    mapping: MmioMapping,
+}
+
+impl IoApic {
+    pub fn new(id: u8, physical_address: PhysAddr, gsi_base: u32) -> Self {
+        let mut io_apic = Self {
+            id,
+            gsi_base,
+            redirection_entries: 0,
+            mapping: memory::map_mmio(physical_address, 0x20, CacheMode::Uncached),
+        };
+
+        io_apic.redirection_entries = io_apic.read(IOAPICVER).get_bits(16..24) + 1;
+        io_apic
+    }
+
+    pub fn id(&self) -> u8 {
+        self.id
+    }
+
+    pub fn handles(&self, gsi: u32) -> bool {
+        gsi >= self.gsi_base && gsi < self.gsi_base + self.redirection_entries
+    }
+
+    pub fn gsi_range(&self) -> core::ops::Range<u32> {
+        self.gsi_base..self.gsi_base + self.redirection_entries
+    }
+
+    pub fn set_redirection(&mut self, gsi: u32, entry: RedirectionEntry) {
+        assert!(
+            self.handles(gsi),
+            "GSI {} not handled by I/O APIC {}",
+            gsi,
+            self.id
+        );
+
+        let register = IOREDTBL + 2 * (gsi - self.gsi_base);
+        let bits = entry.to_bits();
+
+        // Write the high half first so the entry is never live with a stale
+        // destination.
+        self.write(register + 1, bits.get_bits(32..64) as u32);
+        self.write(register, bits.get_bits(0..32) as u32);
+    }
+
+    pub fn set_masked(&mut self, gsi: u32, masked: bool) {
+        assert!(
+            self.handles(gsi),
+            "GSI {} not handled by I/O APIC {}",
+            gsi,
+            self.id
+        );
+
+        let register = IOREDTBL + 2 * (gsi - self.gsi_base);
+        let mut low = self.read(register);
+        low.set_bit(16, masked);
+        self.write(register, low);
+    }
+
+    pub fn mask_all(&mut self) {
+        for gsi in self.gsi_range() {
+            self.set_masked(gsi, true);
+        }
+    }
+
+    fn read(&mut self, register: u32) -> u32 {
+        unsafe {
+            core::ptr::write_volatile(self.register_ptr(IOREGSEL), register);
+            core::ptr::read_volatile(self.register_ptr(IOWIN))
+        }
+    }
+
+    fn write(&mut self, register: u32, value: u32) {
+        unsafe {
+            core::ptr::write_volatile(self.register_ptr(IOREGSEL), register);
+            core::ptr::write_volatile(self.register_ptr(IOWIN), value);
+        }
+    }
+
+    fn register_ptr(&self, offset: usize) -> *mut u32 {
+        unsafe { self.mapping.as_mut_ptr::<u8>().add(offset).cast::<u32>() }
+    }
+}
@@ -0,0 +1,222 @@
+use x86_64::{registers::model_specific::Msr, PhysAddr};
+
+use crate::memory::{self, mmio::CacheMode, mmio::MmioMapping};
+
+const IA32_APIC_BASE: u32 = 0x1B;
+const APIC_BASE_ENABLE: u64 = 1 << 11;
+const SPURIOUS_APIC_ENABLE: u32 = 1 << 8;
+const LVT_MASKED: u32 = 1 << 16;
+const LVT_TIMER_PERIODIC: u32 = 1 << 17;
+const LVT_DELIVERY_NMI: u32 = 0b100 << 8;
+const TIMER_DIVIDE_BY_16: u32 = 0b0011;
+const ICR_DELIVERY_INIT: u32 = 0b101 << 8;
+const ICR_DELIVERY_STARTUP: u32 = 0b110 << 8;
+const ICR_LEVEL_ASSERT: u32 = 1 << 14;
+const ICR_SEND_PENDING: u32 = 1 << 12;
+const ICR_ALL_EXCLUDING_SELF: u32 = 0b11 << 18;
+
+/// The timer always counts at the bus clock divided by this.
+pub const TIMER_DIVISOR: u32 = 16;
+
+#[derive(Debug, Copy, Clone)]
+pub enum LocalApicRegister {
+    Id,
+    Version,
+    TaskPriority,
+    EndOfInterrupt,
+    SpuriousInterruptVector,
+    InService(u8),
+    ErrorStatus,
+    InterruptCommandLow,
+    InterruptCommandHigh,
+    LvtTimer,
+    LvtLint0,
+    LvtLint1,
+    LvtError,
+    TimerInitialCount,
+    TimerCurrentCount,
+    TimerDivideConfiguration,
+}
+
+impl LocalApicRegister {
+    const fn offset(&self) -> usize {
+        match self {
+            LocalApicRegister::Id => 0x20,
+            LocalApicRegister::Version => 0x30,
+            LocalApicRegister::TaskPriority => 0x80,
+            LocalApicRegister::EndOfInterrupt => 0xB0,
+            LocalApicRegister::SpuriousInterruptVector => 0xF0,
+            LocalApicRegister::InService(index) => 0x100 + 0x10 * *index as usize,
+            LocalApicRegister::ErrorStatus => 0x280,
+            LocalApicRegister::InterruptCommandLow => 0x300,
+            LocalApicRegister::InterruptCommandHigh => 0x310,
+            LocalApicRegister::LvtTimer => 0x320,
+            LocalApicRegister::LvtLint0 => 0x350,
+            LocalApicRegister::LvtLint1 => 0x360,
+            LocalApicRegister::LvtError => 0x370,
+            LocalApicRegister::TimerInitialCount => 0x380,
+            LocalApicRegister::TimerCurrentCount => 0x390,
+            LocalApicRegister::TimerDivideConfiguration => 0x3E0,
+        }
+    }
+}
+
+/// The local APIC's two interrupt pins. With the PIC disabled, LINT0 only
+/// carries its ExtINT, and the MADT says which one the NMI is wired to.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum LocalInterruptLine {
+    Lint0,
+    Lint1,
+}
+
+impl LocalInterruptLine {
+    const fn register(&self) -> LocalApicRegister {
+        match self {
+            LocalInterruptLine::Lint0 => LocalApicRegister::LvtLint0,
+            LocalInterruptLine::Lint1 => LocalApicRegister::LvtLint1,
+        }
+    }
+}
+
+/// The memory-mapped local APIC. Every CPU sees its own local APIC at the
+/// same physical address, so one mapping serves all of them.
+pub struct LocalApic {
+    mapping: MmioMapping,
+}
+
+impl LocalApic {
+    pub fn new(physical_address: PhysAddr) -> Self {
+        Self {
+            mapping: memory::map_mmio(physical_address, 0x400, CacheMode::Uncached),
+        }
+    }
+
+    pub fn read(&self, register: LocalApicRegister) -> u32 {
+        unsafe {
+            core::ptr::read_volatile(
+                self.mapping
+                    .as_ptr::<u8>()
+                    .add(register.offset())
+                    .cast::<u32>(),
+            )
+        }
+    }
+
+    pub fn write(&self, register: LocalApicRegister, value: u32) {
+        unsafe {
+            core::ptr::write_volatile(
+                self.mapping
+                    .as_mut_ptr::<u8>()
+                    .add(register.offset())
+                    .cast::<u32>(),
+                value,
+            )
+        }
+    }
+
+    /// Enables the local APIC of the calling CPU and lets it accept every
+    /// interrupt priority.
+    pub fn enable(&self, spurious_vector: u8) {
+        unsafe {
+            let mut apic_base = Msr::new(IA32_APIC_BASE);
+            let value = apic_base.read();
+            apic_base.write(value | APIC_BASE_ENABLE);
+        }
+
+        self.write(LocalApicRegister::LvtTimer, LVT_MASKED);
+        self.write(LocalApicRegister::LvtError, LVT_MASKED);
+        self.write(LocalApicRegister::TaskPriority, 0);
+        self.write(
+            LocalApicRegister::SpuriousInterruptVector,
+            SPURIOUS_APIC_ENABLE | spurious_vector as u32,
+        );
+    }
+
+    /// Delivers an NMI when `nmi_line` is asserted and masks the other pin,
+    /// or both if there's no NMI line.
+    pub fn set_nmi_line(&self, nmi_line: Option<LocalInterruptLine>) {
+        for &line in [LocalInterruptLine::Lint0, LocalInterruptLine::Lint1].iter() {
+            let lvt = if nmi_line == Some(line) {
+                LVT_DELIVERY_NMI
+            } else {
+                LVT_MASKED
+            };
+
+            self.write(line.register(), lvt);
+        }
+    }
+
+    pub fn id(&self) -> u8 {
+        (self.read(LocalApicRegister::Id) >> 24) as u8
+    }
+
+    pub fn version(&self) -> u8 {
+        self.read(LocalApicRegister::Version) as u8
+    }
+
+    pub fn end_of_interrupt(&self) {
+        self.write(LocalApicRegister::EndOfInterrupt, 0);
+    }
+
+    /// Starts the timer counting down from `initial_count`. It raises
+    /// `vector` when it reaches zero, or nothing if `vector` is `None`, and
+    /// reloads itself if `periodic` is set.
+    pub fn start_timer(&self, initial_count: u32, vector: Option<u8>, periodic: bool) {
+        let mut lvt = match vector {
+            Some(vector) => vector as u32,
+            None => LVT_MASKED,
+        };
+        if periodic {
+            lvt |= LVT_TIMER_PERIODIC;
+        }
+
+        self.write(
+            LocalApicRegister::TimerDivideConfiguration,
+            TIMER_DIVIDE_BY_16,
+        );
+        self.write(LocalApicRegister::LvtTimer, lvt);
+        self.write(LocalApicRegister::TimerInitialCount, initial_count);
+    }
+
+    pub fn stop_timer(&self) {
+        self.write(LocalApicRegister::LvtTimer, LVT_MASKED);
+        self.write(LocalApicRegister::TimerInitialCount, 0);
+    }
+
+    pub fn timer_count(&self) -> u32 {
+        self.read(LocalApicRegister::TimerCurrentCount)
+    }
+
+    /// Sends an interprocessor interrupt to the CPU with local APIC ID
+    /// `destination` and waits for it to be accepted.
+    pub fn send_ipi(&self, destination: u8, command: u32) {
+        self.write(
+            LocalApicRegister::InterruptCommandHigh,
+            (destination as u32) << 24,
+        );
+        self.write(LocalApicRegister::InterruptCommandLow, command);
+
+        while self.read(LocalApicRegister::InterruptCommandLow) & ICR_SEND_PENDING != 0 {
+            core::sync::atomic::spin_loop_hint();
+        }
+    }
+
+    /// Raises `vector` on every other processor.
+    pub fn send_ipi_to_others(&self, vector: u8) {
+        self.send_ipi(0, ICR_ALL_EXCLUDING_SELF | vector as u32);
+    }
+
+    /// Resets the processor, leaving it waiting for a startup IPI.
+    pub fn send_init_ipi(&self, destination: u8) {
+        self.send_ipi(destination, ICR_DELIVERY_INIT | ICR_LEVEL_ASSERT);
+    }
+
+    /// Starts a processor that's waiting after an INIT in real mode at
+    /// physical address `page * 4096`.
+    pub fn send_startup_ipi(&self, destination: u8, page: u8) {
+        self.send_ipi(
+            destination,
+            ICR_DELIVERY_STARTUP | ICR_LEVEL_ASSERT | page as u32,
+        );
+    }
+}
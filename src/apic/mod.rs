@@ -0,0 +1,186 @@
+mod io_apic;
+mod local_apic;
+
+use core::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+use ::acpi::{InterruptModel, InterruptSourceOverride};
+use alloc::vec::Vec;
+use spin::{Mutex, Once};
+use x86_64::PhysAddr;
+
+pub use io_apic::{IoApic, Polarity, RedirectionEntry, TriggerMode};
+pub use local_apic::{LocalApic, LocalApicRegister, LocalInterruptLine, TIMER_DIVISOR};
+
+use crate::{acpi, interrupts::irq::irq_vector, pic};
+
+pub const SPURIOUS_VECTOR: u8 = 0xFF;
+
+const ISA_IRQS: u8 = 16;
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static LOCAL_APIC: Once<LocalApic> = Once::new();
+static NMI_LINE: Once<LocalInterruptLine> = Once::new();
+static IO_APICS: Once<Vec<Mutex<IoApic>>> = Once::new();
+static TIMER_FREQUENCY: AtomicU64 = AtomicU64::new(0);
+
+/// Switches interrupt delivery from the 8259 PIC to the local and I/O
+/// APICs described by the ACPI MADT. Leaves the PIC in charge if there's no
+/// MADT.
+pub fn init() {
+    let apic = match acpi::interrupt_model() {
+        Some(InterruptModel::Apic(apic)) => apic,
+        _ => {
+            println!("APIC: no MADT found, using the legacy PIC");
+            return;
+        }
+    };
+
+    x86_64::instructions::interrupts::without_interrupts(|| {
+        pic::disable();
+
+        NMI_LINE.call_once(|| match apic.local_apic_nmi_line {
+            ::acpi::LocalInterruptLine::Lint0 => LocalInterruptLine::Lint0,
+            ::acpi::LocalInterruptLine::Lint1 => LocalInterruptLine::Lint1,
+        });
+
+        let local_apic =
+            LOCAL_APIC.call_once(|| LocalApic::new(PhysAddr::new(apic.local_apic_address)));
+        enable_local_apic(local_apic);
+
+        println!(
+            "APIC: local APIC {} (version {:#x}) at {:#x}",
+            local_apic.id(),
+            local_apic.version(),
+            apic.local_apic_address
+        );
+
+        IO_APICS.call_once(|| {
+            apic.io_apics
+                .iter()
+                .map(|io_apic| {
+                    let mut io_apic = IoApic::new(
+                        io_apic.id,
+                        PhysAddr::new(io_apic.address as u64),
+                        io_apic.global_system_interrupt_base,
+                    );
+                    io_apic.mask_all();
+
+                    println!(
+                        "APIC: I/O APIC {} handling GSIs {:?}",
+                        io_apic.id(),
+                        io_apic.gsi_range()
+                    );
+
+                    Mutex::new(io_apic)
+                })
+                .collect()
+        });
+
+        for isa_irq in 0..ISA_IRQS {
+            if isa_irq == 2 {
+                // The cascade line doesn't exist without the PIC.
+                continue;
+            }
+
+            let source_override = apic
+                .interrupt_source_overrides
+                .iter()
+                .find(|o| o.isa_source == isa_irq);
+            let (gsi, polarity, trigger_mode) = isa_irq_routing(isa_irq, source_override);
+
+            route_gsi(gsi, isa_irq, polarity, trigger_mode);
+        }
+
+        ENABLED.store(true, Ordering::SeqCst);
+    });
+}
+
+/// Enables the calling AP's local APIC, set up like the boot processor's.
+pub fn init_ap() {
+    enable_local_apic(local_apic().expect("AP started before the local APIC is up"));
+}
+
+fn enable_local_apic(local_apic: &LocalApic) {
+    local_apic.enable(SPURIOUS_VECTOR);
+    local_apic.set_nmi_line(NMI_LINE.r#try().copied());
+}
+
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+pub fn local_apic() -> Option<&'static LocalApic> {
+    LOCAL_APIC.r#try()
+}
+
+/// The rate the local APIC timer counts at, once `time::init` has
+/// calibrated it.
+pub fn timer_frequency() -> Option<u64> {
+    match TIMER_FREQUENCY.load(Ordering::Relaxed) {
+        0 => None,
+        frequency => Some(frequency),
+    }
+}
+
+pub fn set_timer_frequency(frequency: u64) {
+    TIMER_FREQUENCY.store(frequency, Ordering::Relaxed);
+}
+
+pub fn end_of_interrupt() {
+    if let Some(local_apic) = local_apic() {
+        local_apic.end_of_interrupt();
+    }
+}
+
+/// Programs the I/O APIC redirection entry for `gsi` to raise `irq` on the
+/// boot processor, unmasked.
+pub fn route_gsi(gsi: u32, irq: u8, polarity: Polarity, trigger_mode: TriggerMode) {
+    let destination = local_apic()
+        .expect("APIC routing requested before the local APIC is up")
+        .id();
+
+    let io_apic = IO_APICS
+        .r#try()
+        .and_then(|io_apics| io_apics.iter().find(|io_apic| io_apic.lock().handles(gsi)));
+
+    match io_apic {
+        Some(io_apic) => io_apic.lock().set_redirection(
+            gsi,
+            RedirectionEntry {
+                vector: irq_vector(irq),
+                polarity,
+                trigger_mode,
+                masked: false,
+                destination,
+            },
+        ),
+        None => println!("APIC: no I/O APIC handles GSI {}", gsi),
+    }
+}
+
+fn isa_irq_routing(
+    isa_irq: u8,
+    source_override: Option<&InterruptSourceOverride>,
+) -> (u32, Polarity, TriggerMode) {
+    // ISA interrupts are edge triggered and active high unless the MADT
+    // says otherwise.
+    match source_override {
+        Some(source_override) => {
+            let polarity = match source_override.polarity {
+                ::acpi::Polarity::ActiveLow => Polarity::ActiveLow,
+                _ => Polarity::ActiveHigh,
+            };
+            let trigger_mode = match source_override.trigger_mode {
+                ::acpi::TriggerMode::Level => TriggerMode::Level,
+                _ => TriggerMode::Edge,
+            };
+
+            (
+                source_override.global_system_interrupt,
+                polarity,
+                trigger_mode,
+            )
+        }
+        None => (isa_irq as u32, Polarity::ActiveHigh, TriggerMode::Edge),
+    }
+}
@@ -0,0 +1,69 @@
+mod symbols;
+
+pub use symbols::{lookup, Symbol};
+
+use x86_64::VirtAddr;
+
+use crate::memory;
+
+const MAX_FRAMES: usize = 64;
+
+/// Prints the return addresses on the current call stack, symbolised where
+/// the kernel was built with its symbol table embedded.
+#[inline(always)]
+pub fn print_backtrace() {
+    let rbp: u64;
+    unsafe { asm!("mov {}, rbp", out(reg) rbp, options(nomem, nostack)) };
+
+    print_backtrace_from(rbp);
+}
+
+pub fn print_backtrace_from(rbp: u64) {
+    println!("Backtrace:");
+
+    for (index, address) in frames(rbp).enumerate() {
+        match symbols::lookup(address) {
+            Some(symbol) => println!(
+                "  {:>2}: {:#018x} {}+{:#x}",
+                index,
+                address,
+                symbol.name,
+                address - symbol.address
+            ),
+            None => println!("  {:>2}: {:#018x} <unknown>", index, address),
+        }
+    }
+}
+
+/// Follows the chain of saved frame pointers from `rbp`, yielding each
+/// frame's return address. Every frame is checked to be mapped before it's
+/// read, since this runs after stack overflows and other corruption.
+pub fn frames(rbp: u64) -> impl Iterator<Item = u64> {
+    let mut rbp = rbp;
+    let mut remaining = MAX_FRAMES;
+
+    core::iter::from_fn(move || {
+        if remaining == 0 || rbp == 0 || rbp % 8 != 0 {
+            return None;
+        }
+
+        let frame = VirtAddr::try_new(rbp).ok()?;
+        if !memory::is_mapped(frame) || !memory::is_mapped(frame + 8u64) {
+            return None;
+        }
+
+        let (next, return_address) = unsafe {
+            let frame = frame.as_ptr::<u64>();
+            (*frame, *frame.add(1))
+        };
+
+        remaining -= 1;
+        rbp = if next == rbp { 0 } else { next };
+
+        if return_address == 0 {
+            None
+        } else {
+            Some(return_address)
+        }
+    })
+}
@@ -0,0 +1,43 @@
+/// The output of `nm -n -C` for the kernel image, embedded by the build
+/// script from the file named by `PANDA_SYMBOLS`. Empty unless the kernel was
+/// built with `scripts/build-with-symbols.sh`.
+static SYMBOLS: &str = include_str!(concat!(env!("OUT_DIR"), "/symbols.txt"));
+
+#[derive(Debug, Copy, Clone)]
+pub struct Symbol {
+    pub address: u64,
+    pub name: &'static str,
+}
+
+/// Finds the function containing `address`.
+pub fn lookup(address: u64) -> Option<Symbol> {
+    let mut closest = None;
+
+    for line in SYMBOLS.lines() {
+        let mut fields = line.splitn(3, ' ');
+
+        let symbol_address = match fields.next().map(|field| u64::from_str_radix(field, 16)) {
+            Some(Ok(symbol_address)) => symbol_address,
+            _ => continue,
+        };
+
+        // Only code symbols; data symbols would swallow return addresses
+        // that fall after them.
+        match (fields.next(), fields.next()) {
+            (Some("t"), Some(name)) | (Some("T"), Some(name)) | (Some("W"), Some(name)) => {
+                // The table is sorted by address.
+                if symbol_address > address {
+                    break;
+                }
+
+                closest = Some(Symbol {
+                    address: symbol_address,
+                    name,
+                });
+            }
+            _ => {}
+        }
+    }
+
+    closest
+}
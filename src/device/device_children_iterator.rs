@@ -1,6 +1,6 @@
 use crate::{
     acpi::AcpiDeviceAddress,
-    pci::{self, PciDeviceAddress},
+    pci::{PciDeviceAddress, SLOTS_PER_BUS},
 };
 
 pub enum DeviceChildrenIterator {
@@ -22,11 +22,7 @@ impl Iterator for DeviceChildrenIterator {
                 parent_pci_address,
                 ref mut next_slot,
             } => {
-                if *next_slot > 0xff {
-                    return None;
-                }
-
-                loop {
+                while *next_slot < SLOTS_PER_BUS as u16 {
                     let address = PciDeviceAddress::new(
                         parent_pci_address.base_address,
                         parent_pci_address.segment,
@@ -41,6 +37,8 @@ impl Iterator for DeviceChildrenIterator {
                         return Some((None, Some(address)));
                     }
                 }
+
+                None
             }
         }
     }
@@ -0,0 +1,34 @@
+use core::fmt::Display;
+
+use aml::AmlError;
+
+use crate::device::DeviceId;
+
+/// Why a driver stopped. Returned by driver futures instead of panicking,
+/// so one broken device doesn't take the kernel down with it.
+#[derive(Debug, Clone)]
+pub enum DriverError {
+    /// The device manager doesn't know the device.
+    NoDevice(DeviceId),
+    /// The firmware's description of the device lacks something the driver
+    /// needs, such as an I/O port or IRQ.
+    MissingResource(&'static str),
+    /// Evaluating or parsing the device's AML failed.
+    Aml(AmlError),
+}
+
+impl From<AmlError> for DriverError {
+    fn from(error: AmlError) -> Self {
+        DriverError::Aml(error)
+    }
+}
+
+impl Display for DriverError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            DriverError::NoDevice(device_id) => write!(f, "{} not found", device_id),
+            DriverError::MissingResource(resource) => write!(f, "no {} given", resource),
+            DriverError::Aml(error) => write!(f, "AML error: {:?}", error),
+        }
+    }
+}
@@ -3,32 +3,63 @@ use aml::{resource::Resource, AmlName};
 use pc_keyboard::DecodedKey;
 use x86_64::instructions::port::Port;
 
+use super::DriverError;
 use crate::{
     acpi,
     device::{device_manager, DeviceId},
-    interrupts::irq::wait_irq,
+    interrupts::irq,
 };
 
 #[derive(Debug)]
 pub struct Scancode(u8);
 
-pub async fn keyboard_task(device_id: DeviceId) {
+pub async fn keyboard_task(device_id: DeviceId) -> Result<(), DriverError> {
     println!("Keyboard task started");
 
+    // Copied out so the device manager isn't locked while the task waits.
+    let (ports, irq) = resources(device_id)?;
+    let command_port = ports
+        .first()
+        .ok_or(DriverError::MissingResource("keyboard I/O port"))?;
+    let mut command_port: Port<u8> = Port::new(*command_port);
+    let irq = irq::register(irq);
+
+    let mut keyboard = pc_keyboard::Keyboard::new(
+        pc_keyboard::layouts::Dvorak104Key,
+        pc_keyboard::ScancodeSet1,
+        pc_keyboard::HandleControl::Ignore,
+    );
+
+    loop {
+        irq.wait().await;
+
+        let scancode: u8 = unsafe { command_port.read() };
+
+        if let Ok(Some(key_event)) = keyboard.add_byte(scancode) {
+            if let Some(key) = keyboard.process_keyevent(key_event) {
+                match key {
+                    DecodedKey::Unicode(character) => print!("{}", character),
+                    DecodedKey::RawKey(key) => print!("{:?}", key),
+                }
+            }
+        }
+    }
+}
+
+fn resources(device_id: DeviceId) -> Result<(Vec<u16>, u8), DriverError> {
     let device_manager = device_manager();
     let device = device_manager
         .get(&device_id)
-        .expect("could not find keyboard device");
+        .ok_or(DriverError::NoDevice(device_id))?;
     let acpi_address = device
         .acpi_address
         .as_ref()
-        .expect("Keyboard does not have ACPI address");
+        .ok_or(DriverError::MissingResource("keyboard ACPI address"))?;
     let crs_name = acpi_address
         .aml_name()
         .child(&AmlName::from_str("_CRS").unwrap());
-    let crs = acpi::get(&crs_name).expect("Could not get keyboard CRS");
-    let resources =
-        aml::resource::resource_descriptor_list(&crs).expect("Could not parse keyboard CRS");
+    let crs = acpi::get(&crs_name)?;
+    let resources = aml::resource::resource_descriptor_list(&crs)?;
 
     let mut ports = Vec::with_capacity(2);
     let mut irq = None;
@@ -41,28 +72,6 @@ pub async fn keyboard_task(device_id: DeviceId) {
         }
     }
 
-    let ports = ports;
-    let mut command_port: Port<u8> = Port::new(ports[0]);
-    let irq = irq.expect("No IRQ given for keyboard") as u8;
-
-    let mut keyboard = pc_keyboard::Keyboard::new(
-        pc_keyboard::layouts::Dvorak104Key,
-        pc_keyboard::ScancodeSet1,
-        pc_keyboard::HandleControl::Ignore,
-    );
-
-    loop {
-        wait_irq(irq).await;
-
-        let scancode: u8 = unsafe { command_port.read() };
-
-        if let Ok(Some(key_event)) = keyboard.add_byte(scancode) {
-            if let Some(key) = keyboard.process_keyevent(key_event) {
-                match key {
-                    DecodedKey::Unicode(character) => print!("{}", character),
-                    DecodedKey::RawKey(key) => print!("{:?}", key),
-                }
-            }
-        }
-    }
+    let irq = irq.ok_or(DriverError::MissingResource("keyboard IRQ"))?;
+    Ok((ports, irq as u8))
 }
@@ -1,14 +1,36 @@
+mod error;
 pub mod keyboard;
+pub mod rtc;
+mod supervisor;
+
+pub use error::DriverError;
+pub use supervisor::{supervise, RestartPolicy};
 
 use keyboard::keyboard_task;
+use rtc::rtc_task;
 
-use crate::task::{Executor, Task};
+use crate::task::{Executor, Priority, Task};
 
 use super::{Device, DeviceKind};
 
 pub fn start_device_driver(executor: &mut Executor, device: &Device) {
+    let device_id = device.id;
+
     match device.kind() {
-        DeviceKind::PcKeyboard => executor.spawn(Task::new(keyboard_task(device.id))),
+        DeviceKind::PcKeyboard => executor.spawn(Task::new(
+            "keyboard",
+            Priority::Interactive,
+            supervise(device_id, "Keyboard", RestartPolicy::DEFAULT, move || {
+                keyboard_task(device_id)
+            }),
+        )),
+        DeviceKind::CmosRtc => executor.spawn(Task::new(
+            "rtc",
+            Priority::BottomHalf,
+            supervise(device_id, "RTC", RestartPolicy::DEFAULT, move || {
+                rtc_task(device_id)
+            }),
+        )),
         DeviceKind::PciBus => {}
         DeviceKind::PciDevice(_) => {}
         DeviceKind::Unknown => {}
@@ -0,0 +1,214 @@
+use bit_field::BitField;
+use x86_64::instructions::port::Port;
+
+use crate::time::DateTime;
+
+const SECONDS: u8 = 0x00;
+const SECONDS_ALARM: u8 = 0x01;
+const MINUTES: u8 = 0x02;
+const MINUTES_ALARM: u8 = 0x03;
+const HOURS: u8 = 0x04;
+const HOURS_ALARM: u8 = 0x05;
+const DAY_OF_MONTH: u8 = 0x07;
+const MONTH: u8 = 0x08;
+const YEAR: u8 = 0x09;
+const STATUS_A: u8 = 0x0A;
+const STATUS_B: u8 = 0x0B;
+const STATUS_C: u8 = 0x0C;
+const STATUS_D: u8 = 0x0D;
+
+// Not standardised, but where every PC-compatible keeps it. The FADT can
+// point elsewhere; nothing seen so far does.
+const CENTURY: u8 = 0x32;
+
+const STATUS_A_UPDATE_IN_PROGRESS: usize = 7;
+const STATUS_B_24_HOUR: usize = 1;
+const STATUS_B_BINARY: usize = 2;
+const STATUS_B_UPDATE_INTERRUPT: usize = 4;
+const STATUS_B_ALARM_INTERRUPT: usize = 5;
+const STATUS_B_PERIODIC_INTERRUPT: usize = 6;
+const HOUR_PM: usize = 7;
+
+// Keeps NMIs disabled while a register is selected, so one can't arrive
+// between selecting and accessing it. The bit latches, so every access
+// ends by clearing it again.
+const NMI_DISABLE: u8 = 0x80;
+
+/// Which interrupt sources were pending, from status register C.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct RtcEvents {
+    pub update_ended: bool,
+    pub alarm: bool,
+    pub periodic: bool,
+}
+
+/// The MC146818-compatible real-time clock in CMOS.
+pub struct Cmos {
+    index: Port<u8>,
+    data: Port<u8>,
+}
+
+impl Cmos {
+    pub fn new(index_port: u16) -> Self {
+        Self {
+            index: Port::new(index_port),
+            data: Port::new(index_port + 1),
+        }
+    }
+
+    fn read(&mut self, register: u8) -> u8 {
+        let value = unsafe {
+            self.index.write(NMI_DISABLE | register);
+            self.data.read()
+        };
+
+        self.enable_nmi();
+        value
+    }
+
+    fn write(&mut self, register: u8, value: u8) {
+        unsafe {
+            self.index.write(NMI_DISABLE | register);
+            self.data.write(value);
+        }
+
+        self.enable_nmi();
+    }
+
+    /// Selects the read-only register D, so a stray data port access can't
+    /// change anything, with NMIs enabled.
+    fn enable_nmi(&mut self) {
+        unsafe {
+            self.index.write(STATUS_D);
+        }
+    }
+
+    fn update_in_progress(&mut self) -> bool {
+        self.read(STATUS_A).get_bit(STATUS_A_UPDATE_IN_PROGRESS)
+    }
+
+    fn read_raw(&mut self) -> [u8; 7] {
+        while self.update_in_progress() {}
+
+        [
+            self.read(SECONDS),
+            self.read(MINUTES),
+            self.read(HOURS),
+            self.read(DAY_OF_MONTH),
+            self.read(MONTH),
+            self.read(YEAR),
+            self.read(CENTURY),
+        ]
+    }
+
+    /// Reads the current date and time. An update can still start part way
+    /// through even after waiting for the flag to clear, so read until two
+    /// consecutive reads agree.
+    pub fn read_date_time(&mut self) -> DateTime {
+        let mut raw = self.read_raw();
+        loop {
+            let again = self.read_raw();
+            if again == raw {
+                break;
+            }
+            raw = again;
+        }
+
+        let [second, minute, hour, day, month, year, century] = raw;
+        let status_b = self.read(STATUS_B);
+        let binary = status_b.get_bit(STATUS_B_BINARY);
+        let decode = |value: u8| {
+            if binary {
+                value
+            } else {
+                (value >> 4) * 10 + (value & 0x0F)
+            }
+        };
+
+        let mut hours = decode(hour & 0x7F);
+        if !status_b.get_bit(STATUS_B_24_HOUR) {
+            // 12 AM is midnight and 12 PM is noon.
+            hours %= 12;
+            if hour.get_bit(HOUR_PM) {
+                hours += 12;
+            }
+        }
+
+        let century = match decode(century) {
+            century @ 19..=99 => century as u16,
+            _ => 20,
+        };
+
+        DateTime {
+            year: century * 100 + decode(year) as u16,
+            month: decode(month),
+            day: decode(day),
+            hour: hours,
+            minute: decode(minute),
+            second: decode(second),
+        }
+    }
+
+    fn encode(&mut self, value: u8) -> u8 {
+        if self.read(STATUS_B).get_bit(STATUS_B_BINARY) {
+            value
+        } else {
+            ((value / 10) << 4) | (value % 10)
+        }
+    }
+
+    fn set_interrupt_enabled(&mut self, bit: usize, enabled: bool) {
+        let mut status_b = self.read(STATUS_B);
+        status_b.set_bit(bit, enabled);
+        self.write(STATUS_B, status_b);
+    }
+
+    /// Raises an interrupt once a second, after each update of the time.
+    pub fn enable_update_interrupt(&mut self, enabled: bool) {
+        self.set_interrupt_enabled(STATUS_B_UPDATE_INTERRUPT, enabled);
+    }
+
+    /// Raises an interrupt every day at `hour:minute:second`, in the same
+    /// 24 hour UTC time as `read_date_time`. Only works when the RTC is in
+    /// 24 hour mode, which is how firmware normally leaves it.
+    pub fn enable_alarm(&mut self, hour: u8, minute: u8, second: u8) {
+        let (hour, minute, second) = (self.encode(hour), self.encode(minute), self.encode(second));
+
+        self.write(HOURS_ALARM, hour);
+        self.write(MINUTES_ALARM, minute);
+        self.write(SECONDS_ALARM, second);
+        self.set_interrupt_enabled(STATUS_B_ALARM_INTERRUPT, true);
+    }
+
+    pub fn disable_alarm(&mut self) {
+        self.set_interrupt_enabled(STATUS_B_ALARM_INTERRUPT, false);
+    }
+
+    /// Raises an interrupt at `32768 >> (rate - 1)` Hz, for `rate` from 3
+    /// (8192 Hz) to 15 (2 Hz), or stops it if `rate` is `None`.
+    pub fn set_periodic_rate(&mut self, rate: Option<u8>) {
+        match rate {
+            Some(rate) => {
+                assert!((3..=15).contains(&rate), "invalid RTC rate {}", rate);
+
+                let mut status_a = self.read(STATUS_A);
+                status_a.set_bits(0..4, rate);
+                self.write(STATUS_A, status_a);
+                self.set_interrupt_enabled(STATUS_B_PERIODIC_INTERRUPT, true);
+            }
+            None => self.set_interrupt_enabled(STATUS_B_PERIODIC_INTERRUPT, false),
+        }
+    }
+
+    /// Reads and clears the pending interrupt flags. The RTC raises no
+    /// further interrupts until this has been done.
+    pub fn acknowledge_interrupt(&mut self) -> RtcEvents {
+        let status_c = self.read(STATUS_C);
+
+        RtcEvents {
+            update_ended: status_c.get_bit(4),
+            alarm: status_c.get_bit(5),
+            periodic: status_c.get_bit(6),
+        }
+    }
+}
@@ -0,0 +1,147 @@
+mod cmos;
+
+pub use cmos::{Cmos, RtcEvents};
+
+use aml::{resource::Resource, AmlName};
+use core::{
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+};
+use spin::{Mutex, Once};
+use x86_64::instructions::interrupts;
+
+use super::DriverError;
+use crate::{
+    acpi,
+    device::{device_manager, DeviceId},
+    interrupts::irq::{self, IrqResult, IrqWaker},
+    task::{self, WaitReason},
+    time,
+};
+
+const DEFAULT_PORT: u16 = 0x70;
+const DEFAULT_IRQ: u8 = 8;
+
+static CMOS: Once<Mutex<Cmos>> = Once::new();
+static ALARM_WAKER: IrqWaker = IrqWaker::new();
+static PERIODIC_WAKER: IrqWaker = IrqWaker::new();
+static UPDATE_ENDED: AtomicBool = AtomicBool::new(false);
+
+pub async fn rtc_task(device_id: DeviceId) -> Result<(), DriverError> {
+    println!("RTC task started");
+
+    let (port, irq) = resources(device_id)?;
+    CMOS.call_once(|| Mutex::new(Cmos::new(port)));
+    let irq = irq::register_handler(irq, handle_interrupt);
+
+    with_cmos(|cmos| {
+        sync_wall_clock(cmos);
+        println!("RTC: wall clock is {}", cmos.read_date_time());
+
+        // Resynchronise at every update, when the time has just ticked over
+        // to a new second.
+        cmos.acknowledge_interrupt();
+        cmos.enable_update_interrupt(true);
+    });
+
+    loop {
+        irq.wait().await;
+
+        if UPDATE_ENDED.swap(false, Ordering::Relaxed) {
+            with_cmos(sync_wall_clock);
+        }
+    }
+}
+
+/// Claims the interrupt if the RTC raised it. Reading status register C is
+/// how to tell, and also acknowledges it, so the events are passed on here.
+fn handle_interrupt(_irq: u8) -> IrqResult {
+    let events = match CMOS.r#try() {
+        Some(cmos) => cmos.lock().acknowledge_interrupt(),
+        None => return IrqResult::NotMine,
+    };
+
+    if events.update_ended {
+        UPDATE_ENDED.store(true, Ordering::Relaxed);
+    }
+    if events.alarm {
+        ALARM_WAKER.wake();
+    }
+    if events.periodic {
+        PERIODIC_WAKER.wake();
+    }
+
+    if events.update_ended || events.alarm || events.periodic {
+        IrqResult::Handled
+    } else {
+        IrqResult::NotMine
+    }
+}
+
+fn resources(device_id: DeviceId) -> Result<(u16, u8), DriverError> {
+    let device_manager = device_manager();
+    let device = device_manager
+        .get(&device_id)
+        .ok_or(DriverError::NoDevice(device_id))?;
+    let acpi_address = device
+        .acpi_address
+        .as_ref()
+        .ok_or(DriverError::MissingResource("RTC ACPI address"))?;
+    let crs_name = acpi_address
+        .aml_name()
+        .child(&AmlName::from_str("_CRS").unwrap());
+
+    let mut port = DEFAULT_PORT;
+    let mut irq = DEFAULT_IRQ;
+
+    match acpi::get(&crs_name).map(|crs| aml::resource::resource_descriptor_list(&crs)) {
+        Ok(Ok(resources)) => {
+            for resource in resources {
+                match resource {
+                    Resource::Irq(irq_descriptor) => irq = irq_descriptor.irq as u8,
+                    Resource::IOPort(io_descriptor) => port = io_descriptor.memory_range.0,
+                    other => println!("Unexpected resource in CRS: {:?}", other),
+                }
+            }
+        }
+        _ => println!(
+            "RTC: no usable CRS, assuming port {:#x} and IRQ {}",
+            port, irq
+        ),
+    }
+
+    Ok((port, irq))
+}
+
+fn sync_wall_clock(cmos: &mut Cmos) {
+    let unix_seconds = cmos.read_date_time().to_unix_seconds();
+    time::set_wall_clock(Duration::from_secs(unix_seconds), time::now());
+}
+
+/// Raises an alarm every day at the given UTC time; see `wait_alarm`.
+pub fn set_alarm(hour: u8, minute: u8, second: u8) {
+    with_cmos(|cmos| cmos.enable_alarm(hour, minute, second));
+}
+
+pub fn clear_alarm() {
+    with_cmos(|cmos| cmos.disable_alarm());
+}
+
+/// Starts or stops the periodic interrupt; see `Cmos::set_periodic_rate`.
+pub fn set_periodic_rate(rate: Option<u8>) {
+    with_cmos(|cmos| cmos.set_periodic_rate(rate));
+}
+
+pub async fn wait_alarm() {
+    task::waiting_on(WaitReason::Other("RTC alarm"), &ALARM_WAKER).await
+}
+
+pub async fn wait_periodic() {
+    task::waiting_on(WaitReason::Other("RTC periodic interrupt"), &PERIODIC_WAKER).await
+}
+
+// The interrupt handler takes the lock too.
+fn with_cmos<R>(f: impl FnOnce(&mut Cmos) -> R) -> R {
+    let cmos = CMOS.r#try().expect("RTC driver hasn't started");
+    interrupts::without_interrupts(|| f(&mut cmos.lock()))
+}
@@ -0,0 +1,77 @@
+use core::{cmp, future::Future, time::Duration};
+
+use super::DriverError;
+use crate::{
+    device::{device_manager, DeviceId, DeviceStatus},
+    time,
+};
+
+/// How often, and how soon, a failed driver is started again.
+#[derive(Debug, Copy, Clone)]
+pub struct RestartPolicy {
+    pub max_restarts: u32,
+    /// The wait before the first restart, doubled for each one after.
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl RestartPolicy {
+    pub const NEVER: RestartPolicy = RestartPolicy {
+        max_restarts: 0,
+        initial_backoff: Duration::from_secs(0),
+        max_backoff: Duration::from_secs(0),
+    };
+
+    pub const DEFAULT: RestartPolicy = RestartPolicy {
+        max_restarts: 5,
+        initial_backoff: Duration::from_millis(100),
+        max_backoff: Duration::from_secs(10),
+    };
+}
+
+/// Runs the driver that `start` creates for `device_id`, keeping its status
+/// in the device manager up to date. When the driver fails the reason is
+/// logged and it's restarted according to `policy`.
+pub async fn supervise<F, D>(device_id: DeviceId, name: &str, policy: RestartPolicy, mut start: F)
+where
+    F: FnMut() -> D,
+    D: Future<Output = Result<(), DriverError>>,
+{
+    let mut restarts = 0;
+    let mut backoff = policy.initial_backoff;
+
+    loop {
+        set_status(device_id, DeviceStatus::Running);
+
+        let error = match start().await {
+            Ok(()) => {
+                set_status(device_id, DeviceStatus::Stopped);
+                return;
+            }
+            Err(error) => error,
+        };
+
+        println!("{} driver for {} failed: {}", name, device_id, error);
+        set_status(device_id, DeviceStatus::Failed(error));
+
+        if restarts == policy.max_restarts {
+            println!("{} driver for {}: giving up", name, device_id);
+            return;
+        }
+
+        println!(
+            "{} driver for {}: restarting in {} ms",
+            name,
+            device_id,
+            backoff.as_millis()
+        );
+        time::sleep(backoff).await;
+
+        restarts += 1;
+        backoff = cmp::min(backoff * 2, policy.max_backoff);
+    }
+}
+
+fn set_status(device_id: DeviceId, status: DeviceStatus) {
+    device_manager().upgrade().set_status(device_id, status);
+}
@@ -10,7 +10,7 @@ use crate::task::Executor;
 
 use aml::{AmlName, AmlValue};
 use device_children_iterator::DeviceChildrenIterator;
-use drivers::start_device_driver;
+use drivers::{start_device_driver, DriverError};
 use lazy_static::lazy_static;
 use pci::{DeviceKind, PciDeviceAddress, PciDeviceKind};
 use spin::{Once, RwLock, RwLockUpgradeableGuard};
@@ -38,6 +38,27 @@ impl Display for DeviceId {
     }
 }
 
+/// Where a device's driver is at, as tracked by its supervisor.
+#[derive(Debug, Clone)]
+pub enum DeviceStatus {
+    /// No driver is running.
+    Stopped,
+    Running,
+    /// The driver returned an error, and is waiting to be restarted or has
+    /// been given up on.
+    Failed(DriverError),
+}
+
+impl Display for DeviceStatus {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            DeviceStatus::Stopped => write!(f, "stopped"),
+            DeviceStatus::Running => write!(f, "running"),
+            DeviceStatus::Failed(error) => write!(f, "failed: {}", error),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Device {
     id: DeviceId,
@@ -61,6 +82,7 @@ impl Device {
             match (name.as_string().as_str(), hid, cid, sub) {
                 ("\\_SB_.PCI0", _, _, _) => return DeviceKind::PciBus,
                 (_, Some(AmlValue::Integer(0x303D041)), _, _) => return DeviceKind::PcKeyboard,
+                (_, Some(AmlValue::Integer(0xBD041)), _, _) => return DeviceKind::CmosRtc,
                 _ => {}
             }
         }
@@ -113,6 +135,7 @@ pub struct DeviceManager {
     id_by_pci_address: HashMap<PciDeviceAddress, DeviceId>,
     acpi_addresses: HashMap<DeviceId, AcpiDeviceAddress>,
     id_by_acpi_address: HashMap<AcpiDeviceAddress, DeviceId>,
+    statuses: HashMap<DeviceId, DeviceStatus>,
 }
 
 impl DeviceManager {
@@ -171,6 +194,26 @@ impl DeviceManager {
         device_id
     }
 
+    /// The status of a known device; `Stopped` until a driver starts.
+    pub fn status(&self, id: &DeviceId) -> Option<DeviceStatus> {
+        if !self.devices.contains(id) {
+            return None;
+        }
+
+        Some(
+            self.statuses
+                .get(id)
+                .cloned()
+                .unwrap_or(DeviceStatus::Stopped),
+        )
+    }
+
+    pub fn set_status(&mut self, id: DeviceId, status: DeviceStatus) {
+        if self.devices.contains(&id) {
+            self.statuses.insert(id, status);
+        }
+    }
+
     fn get(&self, id: &DeviceId) -> Option<Device> {
         if !self.devices.contains(&id) {
             return None;
@@ -1,5 +1,8 @@
+use alloc::boxed::Box;
 use lazy_static::lazy_static;
+use spin::Once;
 use x86_64::{
+    instructions::interrupts,
     instructions::segmentation::set_cs,
     instructions::tables::load_tss,
     structures::{
@@ -9,25 +12,25 @@ use x86_64::{
     VirtAddr,
 };
 
+use crate::memory::stack::KernelStack;
+
 pub const DOUBLE_FAULT_IST_INDEX: u16 = 0;
+pub const NMI_IST_INDEX: u16 = 1;
+pub const MACHINE_CHECK_IST_INDEX: u16 = 2;
+
+// NMIs and machine checks can arrive anywhere, even with the stack pointer
+// at the very bottom of a stack, so they get known good stacks too.
+const IST_STACKS: usize = 3;
+const IST_STACK_PAGES: u64 = 5;
+
+static mut TSS: TaskStateSegment = TaskStateSegment::new();
+static GUARDED_IST_STACKS: Once<[KernelStack; IST_STACKS]> = Once::new();
 
 lazy_static! {
-    static ref TSS: TaskStateSegment = {
-        let mut tss = TaskStateSegment::new();
-        tss.interrupt_stack_table[DOUBLE_FAULT_IST_INDEX as usize] = {
-            const STACK_SIZE: usize = 4096 * 5;
-            static mut STACK: [u8; STACK_SIZE] = [0; STACK_SIZE];
-
-            let stack_start = VirtAddr::from_ptr(unsafe { &STACK });
-            let stack_end = stack_start + STACK_SIZE;
-            stack_end
-        };
-        tss
-    };
     static ref GDT: (GlobalDescriptorTable, Selectors) = {
         let mut gdt = GlobalDescriptorTable::new();
         let code_selector = gdt.add_entry(Descriptor::kernel_code_segment());
-        let tss_selector = gdt.add_entry(Descriptor::tss_segment(&TSS));
+        let tss_selector = gdt.add_entry(Descriptor::tss_segment(unsafe { &TSS }));
 
         (
             gdt,
@@ -45,6 +48,18 @@ pub struct Selectors {
 }
 
 pub fn init() {
+    // Until memory management is up the IST handlers run on static stacks
+    // with no guard page; `init_stacks` replaces them.
+    unsafe {
+        const STACK_SIZE: usize = 4096 * IST_STACK_PAGES as usize;
+        static mut BOOT_STACKS: [[u8; STACK_SIZE]; IST_STACKS] = [[0; STACK_SIZE]; IST_STACKS];
+
+        for (index, stack) in BOOT_STACKS.iter().enumerate() {
+            let stack_start = VirtAddr::from_ptr(stack);
+            TSS.interrupt_stack_table[index] = stack_start + STACK_SIZE;
+        }
+    }
+
     GDT.0.load();
 
     unsafe {
@@ -52,3 +67,44 @@ pub fn init() {
         load_tss(GDT.1.tss_selector);
     }
 }
+
+/// Moves the interrupt stacks onto guarded kernel stacks. Must be called
+/// after `memory::init`.
+pub fn init_stacks() {
+    let stacks = GUARDED_IST_STACKS.call_once(allocate_ist_stacks);
+
+    interrupts::without_interrupts(|| unsafe { set_ist_stacks(&mut TSS, stacks) });
+}
+
+/// Gives an application processor its own GDT and TSS, with interrupt
+/// stacks of its own; the boot processor's are only ever used by it.
+pub fn init_ap() {
+    let stacks = Box::leak(Box::new(allocate_ist_stacks()));
+
+    let tss = Box::leak(Box::new(TaskStateSegment::new()));
+    set_ist_stacks(tss, stacks);
+
+    let gdt = Box::leak(Box::new(GlobalDescriptorTable::new()));
+    let code_selector = gdt.add_entry(Descriptor::kernel_code_segment());
+    let tss_selector = gdt.add_entry(Descriptor::tss_segment(tss));
+    gdt.load();
+
+    unsafe {
+        set_cs(code_selector);
+        load_tss(tss_selector);
+    }
+}
+
+fn allocate_ist_stacks() -> [KernelStack; IST_STACKS] {
+    [
+        KernelStack::allocate(IST_STACK_PAGES),
+        KernelStack::allocate(IST_STACK_PAGES),
+        KernelStack::allocate(IST_STACK_PAGES),
+    ]
+}
+
+fn set_ist_stacks(tss: &mut TaskStateSegment, stacks: &[KernelStack; IST_STACKS]) {
+    for (index, stack) in stacks.iter().enumerate() {
+        tss.interrupt_stack_table[index] = stack.top();
+    }
+}
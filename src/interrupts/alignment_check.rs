@@ -0,0 +1,19 @@
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use x86_64::structures::idt::InterruptStackFrame;
+
+use super::diagnostics;
+
+pub static ALIGNMENT_CHECK_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+pub extern "x86-interrupt" fn alignment_check_handler(
+    stack_frame: &mut InterruptStackFrame,
+    error_code: u64,
+) {
+    ALIGNMENT_CHECK_COUNT.fetch_add(1, Ordering::Relaxed);
+
+    diagnostics::print_exception("ALIGNMENT CHECK", stack_frame);
+    println!("Error Code: {:#x}", error_code);
+
+    panic!("Alignment check at {:?}", stack_frame.instruction_pointer);
+}
@@ -0,0 +1,18 @@
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use x86_64::structures::idt::InterruptStackFrame;
+
+use super::diagnostics;
+
+pub static BOUND_RANGE_EXCEEDED_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+pub extern "x86-interrupt" fn bound_range_exceeded_handler(stack_frame: &mut InterruptStackFrame) {
+    BOUND_RANGE_EXCEEDED_COUNT.fetch_add(1, Ordering::Relaxed);
+
+    diagnostics::print_exception("BOUND RANGE EXCEEDED", stack_frame);
+
+    panic!(
+        "Bound range exceeded at {:?}",
+        stack_frame.instruction_pointer
+    );
+}
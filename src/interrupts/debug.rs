@@ -0,0 +1,13 @@
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use x86_64::structures::idt::InterruptStackFrame;
+
+use super::diagnostics;
+
+pub static DEBUG_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+pub extern "x86-interrupt" fn debug_handler(stack_frame: &mut InterruptStackFrame) {
+    DEBUG_COUNT.fetch_add(1, Ordering::Relaxed);
+
+    diagnostics::print_exception("DEBUG", stack_frame);
+}
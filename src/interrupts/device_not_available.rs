@@ -0,0 +1,18 @@
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use x86_64::structures::idt::InterruptStackFrame;
+
+use super::diagnostics;
+
+pub static DEVICE_NOT_AVAILABLE_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+pub extern "x86-interrupt" fn device_not_available_handler(stack_frame: &mut InterruptStackFrame) {
+    DEVICE_NOT_AVAILABLE_COUNT.fetch_add(1, Ordering::Relaxed);
+
+    diagnostics::print_exception("DEVICE NOT AVAILABLE", stack_frame);
+
+    panic!(
+        "FPU used while unavailable at {:?}",
+        stack_frame.instruction_pointer
+    );
+}
@@ -0,0 +1,88 @@
+use core::fmt::Display;
+
+use bit_field::BitField;
+use x86_64::{
+    registers::control::{Cr0, Cr2, Cr3, Cr4},
+    structures::idt::InterruptStackFrame,
+};
+
+/// Prints the exception name, the stack frame pushed by the CPU and the
+/// control registers at the time of the fault.
+pub fn print_exception(name: &str, stack_frame: &InterruptStackFrame) {
+    println!("EXCEPTION: {}", name);
+    println!("{:#?}", stack_frame);
+    print_control_registers();
+}
+
+/// Like `print_exception`, but for exceptions that can interrupt the
+/// logger on this CPU, like NMIs; prints nothing if it's busy.
+pub fn try_print_exception(name: &str, stack_frame: &InterruptStackFrame) {
+    crate::log::try_print(format_args!(
+        "EXCEPTION: {}\n{:#?}\n{}",
+        name, stack_frame, ControlRegisters
+    ));
+}
+
+pub fn print_control_registers() {
+    print!("{}", ControlRegisters);
+}
+
+struct ControlRegisters;
+
+impl Display for ControlRegisters {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let (level_4_table, cr3_flags) = Cr3::read();
+
+        writeln!(f, "CR0: {:?}", Cr0::read())?;
+        writeln!(f, "CR2: {:?}", Cr2::read())?;
+        writeln!(
+            f,
+            "CR3: {:?} ({:?})",
+            level_4_table.start_address(),
+            cr3_flags
+        )?;
+        writeln!(f, "CR4: {:?}", Cr4::read())
+    }
+}
+
+/// The error code pushed by exceptions that relate to a segment selector
+/// (#TS, #NP, #SS and #GP).
+#[derive(Debug, Copy, Clone)]
+pub struct SelectorErrorCode(pub u64);
+
+impl SelectorErrorCode {
+    /// Whether the exception was raised while delivering an external event
+    /// such as an interrupt, rather than by the instruction itself.
+    pub fn external(&self) -> bool {
+        self.0.get_bit(0)
+    }
+
+    pub fn table(&self) -> &'static str {
+        match self.0.get_bits(1..3) {
+            0b00 => "GDT",
+            0b10 => "LDT",
+            _ => "IDT",
+        }
+    }
+
+    pub fn index(&self) -> u64 {
+        self.0.get_bits(3..16)
+    }
+}
+
+impl Display for SelectorErrorCode {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        if self.0 == 0 {
+            return write!(f, "{:#x} (not selector related)", self.0);
+        }
+
+        write!(
+            f,
+            "{:#x} ({} entry {}{})",
+            self.0,
+            self.table(),
+            self.index(),
+            if self.external() { ", external" } else { "" }
+        )
+    }
+}
@@ -0,0 +1,15 @@
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use x86_64::structures::idt::InterruptStackFrame;
+
+use super::diagnostics;
+
+pub static DIVIDE_ERROR_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+pub extern "x86-interrupt" fn divide_error_handler(stack_frame: &mut InterruptStackFrame) {
+    DIVIDE_ERROR_COUNT.fetch_add(1, Ordering::Relaxed);
+
+    diagnostics::print_exception("DIVIDE ERROR", stack_frame);
+
+    panic!("Divide error at {:?}", stack_frame.instruction_pointer);
+}
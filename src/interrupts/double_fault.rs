@@ -2,6 +2,8 @@ use core::sync::atomic::{AtomicUsize, Ordering};
 
 use x86_64::structures::idt::InterruptStackFrame;
 
+use super::diagnostics;
+
 pub static DOUBLE_FAULT_COUNT: AtomicUsize = AtomicUsize::new(0);
 
 pub extern "x86-interrupt" fn double_fault_handler(
@@ -10,6 +12,8 @@ pub extern "x86-interrupt" fn double_fault_handler(
 ) -> ! {
     DOUBLE_FAULT_COUNT.fetch_add(1, Ordering::Relaxed);
 
+    diagnostics::print_control_registers();
+
     panic!(
         "EXCEPTION: DOUBLE FAULT (error code {})\n{:#?}",
         error_code, stack_frame
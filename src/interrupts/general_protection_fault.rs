@@ -0,0 +1,22 @@
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use x86_64::structures::idt::InterruptStackFrame;
+
+use super::diagnostics::{self, SelectorErrorCode};
+
+pub static GENERAL_PROTECTION_FAULT_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+pub extern "x86-interrupt" fn general_protection_fault_handler(
+    stack_frame: &mut InterruptStackFrame,
+    error_code: u64,
+) {
+    GENERAL_PROTECTION_FAULT_COUNT.fetch_add(1, Ordering::Relaxed);
+
+    diagnostics::print_exception("GENERAL PROTECTION FAULT", stack_frame);
+    println!("Error Code: {}", SelectorErrorCode(error_code));
+
+    panic!(
+        "General protection fault at {:?}",
+        stack_frame.instruction_pointer
+    );
+}
@@ -0,0 +1,15 @@
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use x86_64::structures::idt::InterruptStackFrame;
+
+use super::diagnostics;
+
+pub static INVALID_OPCODE_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+pub extern "x86-interrupt" fn invalid_opcode_handler(stack_frame: &mut InterruptStackFrame) {
+    INVALID_OPCODE_COUNT.fetch_add(1, Ordering::Relaxed);
+
+    diagnostics::print_exception("INVALID OPCODE", stack_frame);
+
+    panic!("Invalid opcode at {:?}", stack_frame.instruction_pointer);
+}
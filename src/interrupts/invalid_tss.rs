@@ -0,0 +1,19 @@
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use x86_64::structures::idt::InterruptStackFrame;
+
+use super::diagnostics::{self, SelectorErrorCode};
+
+pub static INVALID_TSS_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+pub extern "x86-interrupt" fn invalid_tss_handler(
+    stack_frame: &mut InterruptStackFrame,
+    error_code: u64,
+) {
+    INVALID_TSS_COUNT.fetch_add(1, Ordering::Relaxed);
+
+    diagnostics::print_exception("INVALID TSS", stack_frame);
+    println!("Error Code: {}", SelectorErrorCode(error_code));
+
+    panic!("Invalid TSS at {:?}", stack_frame.instruction_pointer);
+}
@@ -1,3 +1,4 @@
+use alloc::{boxed::Box, sync::Arc, vec::Vec};
 use core::{
     future::Future,
     pin::Pin,
@@ -6,9 +7,19 @@ use core::{
 };
 
 use futures_util::task::AtomicWaker;
-use x86_64::structures::idt::InterruptStackFrame;
+use spin::{Mutex, RwLock};
+use x86_64::{
+    instructions::interrupts,
+    structures::idt::{InterruptDescriptorTable, InterruptStackFrame},
+};
+
+use crate::{
+    apic, pic,
+    task::{self, WaitReason},
+    thread,
+};
 
-use crate::pic;
+pub const IRQ_BASE_VECTOR: u8 = 0x20;
 
 pub struct IrqWaker {
     waker: AtomicWaker,
@@ -27,9 +38,17 @@ impl IrqWaker {
         self.waker.register(cx.waker())
     }
 
-    pub fn wake(&self) {
+    /// Returns whether a task was waiting to be woken.
+    pub fn wake(&self) -> bool {
         self.flag.store(true, Ordering::Relaxed);
-        self.waker.wake();
+
+        match self.waker.take() {
+            Some(waker) => {
+                waker.wake();
+                true
+            }
+            None => false,
+        }
     }
 }
 
@@ -41,7 +60,7 @@ impl Future for &IrqWaker {
             return Poll::Ready(());
         }
 
-        self.waker.register(cx.waker());
+        self.register(cx);
 
         if self.flag.compare_and_swap(true, false, Ordering::Relaxed) {
             Poll::Ready(())
@@ -56,20 +75,231 @@ const NUMBER_OF_IRQS: usize = 255 - 32;
 pub static IRQ_COUNT: [AtomicUsize; NUMBER_OF_IRQS] = [AtomicUsize::new(0); NUMBER_OF_IRQS];
 pub static IRQ_WAKER: [IrqWaker; NUMBER_OF_IRQS] = [IrqWaker::new(); NUMBER_OF_IRQS];
 
-pub extern "x86-interrupt" fn irq_handler(_stack_frame: &mut InterruptStackFrame) -> () {
-    let irq = pic::isr() as usize;
+static SUBSCRIBERS: [RwLock<Vec<IrqSubscriber>>; NUMBER_OF_IRQS] =
+    [RwLock::new(Vec::new()); NUMBER_OF_IRQS];
+
+/// What a handler on a shared line reports back after checking whether its
+/// device raised the interrupt.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum IrqResult {
+    Handled,
+    NotMine,
+}
+
+/// Runs in interrupt context with interrupts disabled. It mustn't block,
+/// and may only take locks that are never held with interrupts enabled, like
+/// the heap's, the frame allocator's, the page tables' and the timer queue's;
+/// with those it can allocate and wake tasks, though it may spin while
+/// another CPU holds them. Anything lengthy
+/// belongs in the task it wakes.
+pub type IrqHandler = Box<dyn Fn(u8) -> IrqResult + Send + Sync>;
+
+struct IrqSubscriber {
+    id: usize,
+    handler: Option<IrqHandler>,
+    waker: Arc<IrqWaker>,
+}
+
+/// A driver's subscription to an IRQ line, which any number of drivers can
+/// share. Dropping it unregisters the subscription.
+pub struct IrqRegistration {
+    irq: u8,
+    id: usize,
+    waker: Arc<IrqWaker>,
+}
+
+impl IrqRegistration {
+    pub fn irq(&self) -> u8 {
+        self.irq
+    }
+
+    /// Waits until the line fires and this subscription's handler, if it
+    /// has one, claims the interrupt.
+    pub async fn wait(&self) {
+        task::waiting_on(WaitReason::Irq(self.irq), &*self.waker).await
+    }
+}
+
+impl Drop for IrqRegistration {
+    fn drop(&mut self) {
+        interrupts::without_interrupts(|| {
+            SUBSCRIBERS[self.irq as usize]
+                .write()
+                .retain(|subscriber| subscriber.id != self.id)
+        });
+    }
+}
+
+/// Subscribes to every occurrence of `irq`. Suitable for edge-triggered or
+/// message-signalled lines where there's no device to ask.
+pub fn register(irq: u8) -> IrqRegistration {
+    subscribe(irq, None)
+}
+
+/// Subscribes to `irq` with a handler that's run in interrupt context to
+/// decide whether the device behind the registration raised it, e.g. by
+/// reading and acknowledging its status register. Only when it returns
+/// `IrqResult::Handled` is the registration woken.
+pub fn register_handler<F>(irq: u8, handler: F) -> IrqRegistration
+where
+    F: Fn(u8) -> IrqResult + Send + Sync + 'static,
+{
+    subscribe(irq, Some(Box::new(handler)))
+}
+
+fn subscribe(irq: u8, handler: Option<IrqHandler>) -> IrqRegistration {
+    static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+
+    assert!((irq as usize) < NUMBER_OF_IRQS, "invalid IRQ {}", irq);
+
+    let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+    let waker = Arc::new(IrqWaker::new());
+
+    let subscriber = IrqSubscriber {
+        id,
+        handler,
+        waker: waker.clone(),
+    };
+
+    interrupts::without_interrupts(|| SUBSCRIBERS[irq as usize].write().push(subscriber));
+
+    IrqRegistration { irq, id, waker }
+}
+
+pub fn subscriber_count(irq: u8) -> usize {
+    interrupts::without_interrupts(|| SUBSCRIBERS[irq as usize].read().len())
+}
+
+// IRQs below this are left for the ISA lines and any fixed assignments;
+// MSI vectors are handed out from here up to just below the spurious vector.
+const FIRST_DYNAMIC_IRQ: u8 = 48;
+const LAST_DYNAMIC_IRQ: u8 = 207;
+
+static DYNAMIC_IRQS: Mutex<[bool; NUMBER_OF_IRQS]> = Mutex::new([false; NUMBER_OF_IRQS]);
+
+pub const fn irq_vector(irq: u8) -> u8 {
+    IRQ_BASE_VECTOR + irq
+}
+
+/// Reserves `count` consecutive unused IRQs and returns the first. With
+/// `aligned` set the block starts on a vector that's a multiple of `count`,
+/// as multi-message MSI requires.
+pub fn allocate_irqs(count: u8, aligned: bool) -> Option<u8> {
+    if count == 0 {
+        return None;
+    }
+
+    interrupts::without_interrupts(|| {
+        let mut allocated = DYNAMIC_IRQS.lock();
+
+        let mut first = FIRST_DYNAMIC_IRQ;
+        while first as u16 + count as u16 - 1 <= LAST_DYNAMIC_IRQ as u16 {
+            if aligned && irq_vector(first) % count != 0 {
+                first += 1;
+                continue;
+            }
+
+            let range = first as usize..first as usize + count as usize;
+            if allocated[range.clone()].iter().all(|used| !used) {
+                allocated[range].iter_mut().for_each(|used| *used = true);
+                return Some(first);
+            }
+
+            first += 1;
+        }
+
+        None
+    })
+}
+
+pub fn free_irqs(first: u8, count: u8) {
+    interrupts::without_interrupts(|| {
+        let mut allocated = DYNAMIC_IRQS.lock();
+        for irq in first..first + count {
+            assert!(allocated[irq as usize], "freeing unallocated IRQ {}", irq);
+            allocated[irq as usize] = false;
+        }
+    })
+}
+
+/// Installs one handler per IRQ vector, so the IRQ number is known without
+/// asking the interrupt controller which line is in service.
+pub(super) fn install_handlers(idt: &mut InterruptDescriptorTable) {
+    macro_rules! irq_handlers {
+        ($($irq:literal),* $(,)?) => {
+            $({
+                extern "x86-interrupt" fn handler(_stack_frame: &mut InterruptStackFrame) {
+                    handle_irq($irq);
+                }
+
+                idt[irq_vector($irq) as usize].set_handler_fn(handler);
+            })*
+        };
+    }
+
+    irq_handlers!(
+        0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24,
+        25, 26, 27, 28, 29, 30, 31, 32, 33, 34, 35, 36, 37, 38, 39, 40, 41, 42, 43, 44, 45, 46, 47,
+        48, 49, 50, 51, 52, 53, 54, 55, 56, 57, 58, 59, 60, 61, 62, 63, 64, 65, 66, 67, 68, 69, 70,
+        71, 72, 73, 74, 75, 76, 77, 78, 79, 80, 81, 82, 83, 84, 85, 86, 87, 88, 89, 90, 91, 92, 93,
+        94, 95, 96, 97, 98, 99, 100, 101, 102, 103, 104, 105, 106, 107, 108, 109, 110, 111, 112,
+        113, 114, 115, 116, 117, 118, 119, 120, 121, 122, 123, 124, 125, 126, 127, 128, 129, 130,
+        131, 132, 133, 134, 135, 136, 137, 138, 139, 140, 141, 142, 143, 144, 145, 146, 147, 148,
+        149, 150, 151, 152, 153, 154, 155, 156, 157, 158, 159, 160, 161, 162, 163, 164, 165, 166,
+        167, 168, 169, 170, 171, 172, 173, 174, 175, 176, 177, 178, 179, 180, 181, 182, 183, 184,
+        185, 186, 187, 188, 189, 190, 191, 192, 193, 194, 195, 196, 197, 198, 199, 200, 201, 202,
+        203, 204, 205, 206, 207, 208, 209, 210, 211, 212, 213, 214, 215, 216, 217, 218, 219, 220,
+        221, 222,
+    );
+
+    idt[apic::SPURIOUS_VECTOR as usize].set_handler_fn(spurious_handler);
+}
+
+fn handle_irq(irq: u8) {
+    IRQ_COUNT[irq as usize].fetch_add(1, Ordering::Relaxed);
+
+    // Lines used through `wait_irq` count as handled while a task is
+    // waiting on them.
+    let mut handled = IRQ_WAKER[irq as usize].wake();
+    for subscriber in SUBSCRIBERS[irq as usize].read().iter() {
+        let result = match &subscriber.handler {
+            Some(handler) => handler(irq),
+            None => IrqResult::Handled,
+        };
+
+        if result == IrqResult::Handled {
+            subscriber.waker.wake();
+            handled = true;
+        }
+    }
 
     match irq {
-        1 | 2 => {}
+        0 | 1 => {}
+        _ if handled => {}
         _ => println!("IRQ {}", irq),
     }
 
-    IRQ_COUNT[irq].fetch_add(1, Ordering::Relaxed);
-    IRQ_WAKER[irq].wake();
+    end_of_interrupt(irq);
 
-    pic::notify_end_of_irq(irq as u8);
+    // Only once the interrupt is acknowledged, or the next thread wouldn't
+    // get any more.
+    thread::preempt_if_needed();
 }
 
-pub(crate) async fn wait_irq(irq: u8) {
-    (&IRQ_WAKER[irq as usize]).await
+fn end_of_interrupt(irq: u8) {
+    if apic::is_enabled() {
+        apic::end_of_interrupt();
+    } else {
+        pic::notify_end_of_irq(irq);
+    }
+}
+
+// Spurious interrupts from the local APIC must not be acknowledged.
+extern "x86-interrupt" fn spurious_handler(_stack_frame: &mut InterruptStackFrame) {}
+
+/// Waits for the next occurrence of `irq` on the line's single built-in
+/// waker. Only for drivers that own their line outright; anything that may
+/// share it should hold an `IrqRegistration` instead.
+pub async fn wait_irq(irq: u8) {
+    task::waiting_on(WaitReason::Irq(irq), &IRQ_WAKER[irq as usize]).await
 }
@@ -0,0 +1,44 @@
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use bit_field::BitField;
+use x86_64::{registers::model_specific::Msr, structures::idt::InterruptStackFrame};
+
+use super::diagnostics;
+
+const IA32_MCG_CAP: u32 = 0x179;
+const IA32_MCG_STATUS: u32 = 0x17A;
+const IA32_MC0_STATUS: u32 = 0x401;
+const IA32_MC0_ADDR: u32 = 0x402;
+
+const MCI_STATUS_VALID: usize = 63;
+const MCI_STATUS_ADDR_VALID: usize = 58;
+
+pub static MACHINE_CHECK_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+pub extern "x86-interrupt" fn machine_check_handler(stack_frame: &mut InterruptStackFrame) -> ! {
+    MACHINE_CHECK_COUNT.fetch_add(1, Ordering::Relaxed);
+
+    diagnostics::print_exception("MACHINE CHECK", stack_frame);
+
+    unsafe {
+        let bank_count = Msr::new(IA32_MCG_CAP).read().get_bits(0..8) as u32;
+        println!("MCG_STATUS: {:#x}", Msr::new(IA32_MCG_STATUS).read());
+
+        // Each bank has four MSRs: control, status, address and misc.
+        for bank in 0..bank_count {
+            let status = Msr::new(IA32_MC0_STATUS + 4 * bank).read();
+            if !status.get_bit(MCI_STATUS_VALID) {
+                continue;
+            }
+
+            if status.get_bit(MCI_STATUS_ADDR_VALID) {
+                let address = Msr::new(IA32_MC0_ADDR + 4 * bank).read();
+                println!("MC{}_STATUS: {:#x} (address {:#x})", bank, status, address);
+            } else {
+                println!("MC{}_STATUS: {:#x}", bank, status);
+            }
+        }
+    }
+
+    panic!("Machine check at {:?}", stack_frame.instruction_pointer);
+}
@@ -1,9 +1,26 @@
+pub mod alignment_check;
+pub mod bound_range_exceeded;
 pub mod breakpoint;
+pub mod debug;
+pub mod device_not_available;
+pub mod diagnostics;
+pub mod divide_error;
 pub mod double_fault;
+pub mod general_protection_fault;
+pub mod invalid_opcode;
+pub mod invalid_tss;
 pub mod irq;
+pub mod machine_check;
+pub mod non_maskable_interrupt;
+pub mod overflow;
 pub mod page_fault;
+pub mod security_exception;
+pub mod segment_not_present;
+pub mod simd_floating_point;
+pub mod stack_segment_fault;
+pub mod virtualization;
+pub mod x87_floating_point;
 
-use irq::irq_handler;
 use lazy_static::lazy_static;
 use x86_64::structures::idt::InterruptDescriptorTable;
 
@@ -12,21 +29,53 @@ use crate::gdt;
 lazy_static! {
     static ref IDT: InterruptDescriptorTable = {
         let mut idt = InterruptDescriptorTable::new();
+        idt.divide_error
+            .set_handler_fn(divide_error::divide_error_handler);
+        idt.debug.set_handler_fn(debug::debug_handler);
         idt.breakpoint
             .set_handler_fn(breakpoint::breakpoint_handler);
+        idt.overflow.set_handler_fn(overflow::overflow_handler);
+        idt.bound_range_exceeded
+            .set_handler_fn(bound_range_exceeded::bound_range_exceeded_handler);
+        idt.invalid_opcode
+            .set_handler_fn(invalid_opcode::invalid_opcode_handler);
+        idt.device_not_available
+            .set_handler_fn(device_not_available::device_not_available_handler);
 
         unsafe {
             idt.double_fault
                 .set_handler_fn(double_fault::double_fault_handler)
                 .set_stack_index(gdt::DOUBLE_FAULT_IST_INDEX);
+            idt.non_maskable_interrupt
+                .set_handler_fn(non_maskable_interrupt::non_maskable_interrupt_handler)
+                .set_stack_index(gdt::NMI_IST_INDEX);
+            idt.machine_check
+                .set_handler_fn(machine_check::machine_check_handler)
+                .set_stack_index(gdt::MACHINE_CHECK_IST_INDEX);
         }
 
+        idt.invalid_tss
+            .set_handler_fn(invalid_tss::invalid_tss_handler);
+        idt.segment_not_present
+            .set_handler_fn(segment_not_present::segment_not_present_handler);
+        idt.stack_segment_fault
+            .set_handler_fn(stack_segment_fault::stack_segment_fault_handler);
+        idt.general_protection_fault
+            .set_handler_fn(general_protection_fault::general_protection_fault_handler);
         idt.page_fault
             .set_handler_fn(page_fault::page_fault_handler);
+        idt.x87_floating_point
+            .set_handler_fn(x87_floating_point::x87_floating_point_handler);
+        idt.alignment_check
+            .set_handler_fn(alignment_check::alignment_check_handler);
+        idt.simd_floating_point
+            .set_handler_fn(simd_floating_point::simd_floating_point_handler);
+        idt.virtualization
+            .set_handler_fn(virtualization::virtualization_handler);
+        idt.security_exception
+            .set_handler_fn(security_exception::security_exception_handler);
 
-        for irq in 0..=255 - 32 {
-            idt[irq + 0x20].set_handler_fn(irq_handler);
-        }
+        irq::install_handlers(&mut idt);
 
         idt
     };
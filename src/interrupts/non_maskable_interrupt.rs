@@ -0,0 +1,17 @@
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use x86_64::structures::idt::InterruptStackFrame;
+
+use super::diagnostics;
+
+pub static NON_MASKABLE_INTERRUPT_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+pub extern "x86-interrupt" fn non_maskable_interrupt_handler(
+    stack_frame: &mut InterruptStackFrame,
+) {
+    NON_MASKABLE_INTERRUPT_COUNT.fetch_add(1, Ordering::Relaxed);
+
+    // An NMI can arrive while this CPU holds the console, so never wait
+    // for it.
+    diagnostics::try_print_exception("NON-MASKABLE INTERRUPT", stack_frame);
+}
@@ -0,0 +1,15 @@
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use x86_64::structures::idt::InterruptStackFrame;
+
+use super::diagnostics;
+
+pub static OVERFLOW_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+pub extern "x86-interrupt" fn overflow_handler(stack_frame: &mut InterruptStackFrame) {
+    OVERFLOW_COUNT.fetch_add(1, Ordering::Relaxed);
+
+    diagnostics::print_exception("OVERFLOW", stack_frame);
+
+    panic!("Overflow at {:?}", stack_frame.instruction_pointer);
+}
@@ -1,12 +1,8 @@
 use core::sync::atomic::{AtomicUsize, Ordering};
 
-use x86_64::structures::{
-    idt::{InterruptStackFrame, PageFaultErrorCode},
-    paging::Page,
-    paging::PageTableFlags,
-};
+use x86_64::structures::idt::{InterruptStackFrame, PageFaultErrorCode};
 
-use crate::memory::{self, HEAP_SIZE, HEAP_START};
+use super::diagnostics;
 
 pub static PAGE_FAULT_COUNT: AtomicUsize = AtomicUsize::new(0);
 
@@ -20,23 +16,9 @@ pub extern "x86-interrupt" fn page_fault_handler(
 
     let address = Cr2::read();
 
-    if address.as_u64() >= HEAP_START && address.as_u64() - HEAP_START <= HEAP_SIZE {
-        unsafe {
-            memory::map_page(
-                Page::containing_address(address),
-                PageTableFlags::GLOBAL
-                    | PageTableFlags::PRESENT
-                    | PageTableFlags::NO_EXECUTE
-                    | PageTableFlags::WRITABLE,
-            );
-        }
-        return;
-    }
-
-    println!("EXCEPTION: PAGE FAULT");
+    diagnostics::print_exception("PAGE FAULT", stack_frame);
     println!("Accessed Address: {:?}", address);
     println!("Error Code: {:?}", error_code);
-    println!("{:#?}", stack_frame);
 
     panic!("Invalid page fault");
 }
@@ -0,0 +1,22 @@
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use x86_64::structures::idt::InterruptStackFrame;
+
+use super::diagnostics;
+
+pub static SECURITY_EXCEPTION_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+pub extern "x86-interrupt" fn security_exception_handler(
+    stack_frame: &mut InterruptStackFrame,
+    error_code: u64,
+) {
+    SECURITY_EXCEPTION_COUNT.fetch_add(1, Ordering::Relaxed);
+
+    diagnostics::print_exception("SECURITY EXCEPTION", stack_frame);
+    println!("Error Code: {:#x}", error_code);
+
+    panic!(
+        "Security exception at {:?}",
+        stack_frame.instruction_pointer
+    );
+}
@@ -0,0 +1,22 @@
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use x86_64::structures::idt::InterruptStackFrame;
+
+use super::diagnostics::{self, SelectorErrorCode};
+
+pub static SEGMENT_NOT_PRESENT_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+pub extern "x86-interrupt" fn segment_not_present_handler(
+    stack_frame: &mut InterruptStackFrame,
+    error_code: u64,
+) {
+    SEGMENT_NOT_PRESENT_COUNT.fetch_add(1, Ordering::Relaxed);
+
+    diagnostics::print_exception("SEGMENT NOT PRESENT", stack_frame);
+    println!("Error Code: {}", SelectorErrorCode(error_code));
+
+    panic!(
+        "Segment not present at {:?}",
+        stack_frame.instruction_pointer
+    );
+}
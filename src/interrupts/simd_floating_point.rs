@@ -0,0 +1,18 @@
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use x86_64::structures::idt::InterruptStackFrame;
+
+use super::diagnostics;
+
+pub static SIMD_FLOATING_POINT_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+pub extern "x86-interrupt" fn simd_floating_point_handler(stack_frame: &mut InterruptStackFrame) {
+    SIMD_FLOATING_POINT_COUNT.fetch_add(1, Ordering::Relaxed);
+
+    diagnostics::print_exception("SIMD FLOATING POINT", stack_frame);
+
+    panic!(
+        "SIMD floating point exception at {:?}",
+        stack_frame.instruction_pointer
+    );
+}
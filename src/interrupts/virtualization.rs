@@ -0,0 +1,18 @@
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use x86_64::structures::idt::InterruptStackFrame;
+
+use super::diagnostics;
+
+pub static VIRTUALIZATION_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+pub extern "x86-interrupt" fn virtualization_handler(stack_frame: &mut InterruptStackFrame) {
+    VIRTUALIZATION_COUNT.fetch_add(1, Ordering::Relaxed);
+
+    diagnostics::print_exception("VIRTUALIZATION", stack_frame);
+
+    panic!(
+        "Virtualization exception at {:?}",
+        stack_frame.instruction_pointer
+    );
+}
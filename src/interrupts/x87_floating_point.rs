@@ -0,0 +1,18 @@
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use x86_64::structures::idt::InterruptStackFrame;
+
+use super::diagnostics;
+
+pub static X87_FLOATING_POINT_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+pub extern "x86-interrupt" fn x87_floating_point_handler(stack_frame: &mut InterruptStackFrame) {
+    X87_FLOATING_POINT_COUNT.fetch_add(1, Ordering::Relaxed);
+
+    diagnostics::print_exception("x87 FLOATING POINT", stack_frame);
+
+    panic!(
+        "x87 floating point exception at {:?}",
+        stack_frame.instruction_pointer
+    );
+}
@@ -4,6 +4,8 @@
 #![reexport_test_harness_main = "test_main"]
 #![feature(custom_test_frameworks)]
 #![feature(alloc_error_handler)]
+#![feature(asm)]
+#![feature(global_asm)]
 #![feature(const_in_array_repeat_expressions)]
 #![feature(abi_x86_interrupt)]
 #![feature(wake_trait)]
@@ -20,6 +22,8 @@ pub mod serial;
 pub mod test_runner;
 
 pub mod acpi;
+pub mod apic;
+pub mod backtrace;
 pub mod device;
 pub mod gdt;
 pub mod interrupts;
@@ -28,7 +32,11 @@ pub mod panic;
 pub mod pci;
 pub mod pic;
 pub mod qemu;
+pub mod smp;
+pub mod sync;
 pub mod task;
+pub mod thread;
+pub mod time;
 pub mod vga;
 
 pub trait Testable {
@@ -3,7 +3,7 @@ use core::fmt::Write;
 use lazy_static::lazy_static;
 use spin::Mutex;
 
-use crate::{serial_print, vga::Vga};
+use crate::{serial::SERIAL1, serial_print, vga::Vga};
 
 pub enum LogTarget {
     Null,
@@ -29,6 +29,37 @@ pub fn set_log_target(target: LogTarget) {
     *TARGET.lock() = target;
 }
 
+/// Gives VGA output a write-combining mapping once memory management is up,
+/// instead of the cacheable physical memory map.
+pub fn map_vga() {
+    x86_64::instructions::interrupts::without_interrupts(|| {
+        if let LogTarget::Vga(vga) = &mut *TARGET.lock() {
+            vga.map_write_combining();
+        }
+    })
+}
+
+/// Prints only if neither the log target nor the serial port is locked, for
+/// code that may have interrupted whoever holds them on this CPU. Returns
+/// whether it printed.
+pub fn try_print(args: core::fmt::Arguments) -> bool {
+    let mut target = match TARGET.try_lock() {
+        Some(target) => target,
+        None => return false,
+    };
+    let mut serial = match SERIAL1.try_lock() {
+        Some(serial) => serial,
+        None => return false,
+    };
+
+    let _ = serial.write_fmt(args);
+    if let LogTarget::Vga(vga) = &mut *target {
+        let _ = vga.writer.write_fmt(args);
+    }
+
+    true
+}
+
 #[macro_export]
 macro_rules! println {
     () => (print!("\n"));
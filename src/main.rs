@@ -35,8 +35,14 @@ pub extern "C" fn _start(bootinfo: &'static bootloader::BootInfo) -> ! {
     interrupts::init();
     pic::init();
     memory::init(&bootinfo);
+    log::map_vga();
+    gdt::init_stacks();
     device::init();
     acpi::init();
+    apic::init();
+    time::init(time::DEFAULT_TIMER_FREQUENCY);
+    thread::init();
+    smp::init();
     pci::init();
 
     let mut executor = task::init();
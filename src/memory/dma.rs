@@ -0,0 +1,119 @@
+use x86_64::{structures::paging::PhysFrame, PhysAddr, VirtAddr};
+
+use super::{frame_allocator::FRAME_SIZE, physical_to_virtual_address};
+
+const FOUR_GIB: u64 = 0x1_0000_0000;
+
+#[derive(Debug, Copy, Clone)]
+pub struct DmaConstraints {
+    /// Required alignment of the physical start address, in bytes. Anything
+    /// below a frame is rounded up to one.
+    pub alignment: usize,
+
+    /// Set for devices that can only generate 32-bit addresses.
+    pub below_4gib: bool,
+}
+
+impl Default for DmaConstraints {
+    fn default() -> Self {
+        Self {
+            alignment: FRAME_SIZE as usize,
+            below_4gib: false,
+        }
+    }
+}
+
+/// A zeroed, physically contiguous buffer for bus-mastering devices. The
+/// CPU accesses it through the physical memory mapping, so the device sees
+/// exactly the bytes the driver writes at `virtual_address()`.
+#[derive(Debug)]
+pub struct DmaBuffer {
+    physical_address: PhysAddr,
+    virtual_address: VirtAddr,
+    len: usize,
+    frames: usize,
+}
+
+impl DmaBuffer {
+    pub fn allocate(len: usize, constraints: DmaConstraints) -> Option<Self> {
+        assert!(len > 0, "can't allocate an empty DMA buffer");
+        assert!(
+            constraints.alignment.is_power_of_two(),
+            "DMA alignment must be a power of two"
+        );
+
+        let frames = (len + FRAME_SIZE as usize - 1) / FRAME_SIZE as usize;
+        let alignment = (constraints.alignment / FRAME_SIZE as usize).max(1);
+        let start = if constraints.below_4gib {
+            super::with_frame_allocator(|frame_allocator| {
+                frame_allocator.allocate_contiguous_below(
+                    frames,
+                    alignment,
+                    PhysAddr::new(FOUR_GIB),
+                )
+            })?
+        } else {
+            super::with_frame_allocator(|frame_allocator| {
+                frame_allocator.allocate_contiguous(frames, alignment)
+            })?
+        };
+
+        let physical_address = start.start_address();
+        let virtual_address = physical_to_virtual_address(physical_address);
+
+        unsafe {
+            core::ptr::write_bytes(
+                virtual_address.as_mut_ptr::<u8>(),
+                0,
+                frames * FRAME_SIZE as usize,
+            );
+        }
+
+        Some(Self {
+            physical_address,
+            virtual_address,
+            len,
+            frames,
+        })
+    }
+
+    /// The address to program into the device.
+    pub fn physical_address(&self) -> PhysAddr {
+        self.physical_address
+    }
+
+    pub fn virtual_address(&self) -> VirtAddr {
+        self.virtual_address
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn as_ptr<T>(&self) -> *const T {
+        self.virtual_address.as_ptr()
+    }
+
+    pub fn as_mut_ptr<T>(&mut self) -> *mut T {
+        self.virtual_address.as_mut_ptr()
+    }
+
+    pub fn as_slice(&self) -> &[u8] {
+        unsafe { core::slice::from_raw_parts(self.as_ptr(), self.len) }
+    }
+
+    pub fn as_mut_slice(&mut self) -> &mut [u8] {
+        unsafe { core::slice::from_raw_parts_mut(self.as_mut_ptr(), self.len) }
+    }
+}
+
+impl Drop for DmaBuffer {
+    fn drop(&mut self) {
+        super::with_frame_allocator(|frame_allocator| unsafe {
+            frame_allocator.deallocate_contiguous(
+                PhysFrame::containing_address(self.physical_address),
+                self.frames,
+            )
+        });
+    }
+}
@@ -1,39 +1,331 @@
+use core::ops::Range;
+
 use bootloader::bootinfo::{MemoryMap, MemoryRegionType};
 use x86_64::{
-    structures::paging::{FrameAllocator, PhysFrame, Size4KiB},
+    structures::paging::{
+        FrameAllocator, FrameDeallocator, PageSize, PhysFrame, Size1GiB, Size2MiB, Size4KiB,
+    },
     PhysAddr,
 };
 
+use super::physical_to_virtual_address;
+
+pub const FRAME_SIZE: u64 = 4096;
+
+const BITS_PER_WORD: usize = 64;
+const MAX_RESERVATIONS: usize = 16;
+
+/// Tracks every physical frame below the end of the highest usable region in
+/// a bitmap (one bit per frame, set when the frame is in use or reserved).
+///
+/// The bitmap itself lives in the first usable region large enough to hold
+/// it and is accessed through the physical memory mapping.
 pub struct PhysicalFrameAllocator {
-    memory_map: &'static MemoryMap,
+    bitmap: &'static mut [u64],
+    frame_count: usize,
+    usable_frames: usize,
+    allocated_frames: usize,
+    reserved_frames: usize,
+    reservations: [Option<Range<PhysAddr>>; MAX_RESERVATIONS],
     next: usize,
 }
 
 impl PhysicalFrameAllocator {
-    pub const fn new(memory_map: &'static MemoryMap) -> Self {
-        Self {
-            memory_map,
+    pub fn new(memory_map: &'static MemoryMap) -> Self {
+        let usable_regions = || {
+            memory_map
+                .iter()
+                .filter(|r| r.region_type == MemoryRegionType::Usable)
+        };
+
+        let end_address = usable_regions()
+            .map(|r| r.range.end_addr())
+            .max()
+            .expect("No usable memory regions");
+
+        let frame_count = (end_address / FRAME_SIZE) as usize;
+        let bitmap_words = (frame_count + BITS_PER_WORD - 1) / BITS_PER_WORD;
+        let bitmap_bytes = (bitmap_words * core::mem::size_of::<u64>()) as u64;
+        let bitmap_frames = ((bitmap_bytes + FRAME_SIZE - 1) / FRAME_SIZE) as usize;
+
+        let bitmap_region = usable_regions()
+            .find(|r| r.range.end_frame_number - r.range.start_frame_number >= bitmap_frames as u64)
+            .expect("No usable memory region large enough for the frame bitmap");
+        let bitmap_start = PhysAddr::new(bitmap_region.range.start_addr());
+
+        let bitmap = unsafe {
+            core::slice::from_raw_parts_mut(
+                physical_to_virtual_address(bitmap_start).as_mut_ptr::<u64>(),
+                bitmap_words,
+            )
+        };
+
+        for word in bitmap.iter_mut() {
+            *word = !0;
+        }
+
+        let mut allocator = Self {
+            bitmap,
+            frame_count,
+            usable_frames: 0,
+            allocated_frames: 0,
+            reserved_frames: 0,
+            reservations: Default::default(),
             next: 0,
+        };
+
+        for region in usable_regions() {
+            let start = region.range.start_frame_number as usize;
+            let end = region.range.end_frame_number as usize;
+
+            for index in start..end {
+                allocator.set_used(index, false);
+            }
+            allocator.usable_frames += end - start;
         }
+
+        allocator.reserve(bitmap_start..bitmap_start + bitmap_frames as u64 * FRAME_SIZE);
+
+        allocator
     }
 
-    pub fn frames<'a>(&'a self) -> impl Iterator<Item = PhysFrame> {
-        let regions = self
-            .memory_map
-            .iter()
-            .filter(|r| r.region_type == MemoryRegionType::Usable);
+    /// Number of frames covered by the bitmap, whether usable or not.
+    pub fn frame_count(&self) -> usize {
+        self.frame_count
+    }
+
+    /// Number of frames the bootloader reported as usable.
+    pub fn usable_frames(&self) -> usize {
+        self.usable_frames
+    }
 
-        let ranges = regions.map(|r| r.range.start_addr()..r.range.end_addr());
-        let frame_addresses = ranges.flat_map(|r| r.step_by(4096));
+    pub fn allocated_frames(&self) -> usize {
+        self.allocated_frames
+    }
+
+    /// Number of usable frames taken out of circulation by `reserve`.
+    pub fn reserved_frames(&self) -> usize {
+        self.reserved_frames
+    }
+
+    pub fn free_frames(&self) -> usize {
+        self.usable_frames - self.allocated_frames - self.reserved_frames
+    }
+
+    pub fn reservations(&self) -> impl Iterator<Item = &Range<PhysAddr>> {
+        self.reservations.iter().filter_map(|r| r.as_ref())
+    }
+
+    /// Permanently removes the frames in `range` from the pool, e.g. for
+    /// firmware tables or trampolines that must stay at a fixed address.
+    pub fn reserve(&mut self, range: Range<PhysAddr>) {
+        let start = (range.start.as_u64() / FRAME_SIZE) as usize;
+        let end = ((range.end.as_u64() + FRAME_SIZE - 1) / FRAME_SIZE) as usize;
+
+        for index in start..end.min(self.frame_count) {
+            if !self.is_used(index) {
+                self.set_used(index, true);
+                self.reserved_frames += 1;
+            }
+        }
+
+        // Recorded so reserved frames can't be freed into the pool.
+        let slot = self
+            .reservations
+            .iter_mut()
+            .find(|r| r.is_none())
+            .expect("Too many frame reservations");
+        *slot = Some(range);
+    }
+
+    /// Allocates `count` physically contiguous frames whose first frame
+    /// number is a multiple of `alignment` (in frames).
+    pub fn allocate_contiguous(&mut self, count: usize, alignment: usize) -> Option<PhysFrame> {
+        self.allocate_contiguous_before(count, alignment, self.frame_count)
+    }
+
+    /// Like `allocate_contiguous`, but every frame returned ends at or below
+    /// `limit`, for devices that can only address part of physical memory.
+    pub fn allocate_contiguous_below(
+        &mut self,
+        count: usize,
+        alignment: usize,
+        limit: PhysAddr,
+    ) -> Option<PhysFrame> {
+        let end = self.frame_count.min((limit.as_u64() / FRAME_SIZE) as usize);
+        self.allocate_contiguous_before(count, alignment, end)
+    }
+
+    fn allocate_contiguous_before(
+        &mut self,
+        count: usize,
+        alignment: usize,
+        end: usize,
+    ) -> Option<PhysFrame> {
+        assert!(count > 0, "can't allocate zero frames");
+        assert!(
+            alignment.is_power_of_two(),
+            "frame alignment must be a power of two"
+        );
+
+        let mut start = 0;
+        while start + count <= end {
+            match (start..start + count)
+                .rev()
+                .find(|&index| self.is_used(index))
+            {
+                Some(used) => start = align_up(used + 1, alignment),
+                None => {
+                    for index in start..start + count {
+                        self.set_used(index, true);
+                    }
+                    self.allocated_frames += count;
+                    return Some(frame_for_index(start));
+                }
+            }
+        }
+
+        None
+    }
+
+    /// Returns `count` frames starting at `start` to the pool.
+    ///
+    /// Safety: the frames must have been allocated from this allocator and
+    /// must no longer be referenced by any mapping.
+    pub unsafe fn deallocate_contiguous(&mut self, start: PhysFrame, count: usize) {
+        let start = index_for_frame(start);
+        let frames = start..start + count;
+
+        // Check the whole range first, so a bad free leaves the bitmap and
+        // counters as they were.
+        assert!(
+            frames.end <= self.frame_count && count <= self.allocated_frames,
+            "freeing {} frames from {:?}, which were never allocated",
+            count,
+            frame_for_index(start)
+        );
+        if let Some(index) = frames.clone().find(|&index| !self.is_used(index)) {
+            panic!("double free of frame {:?}", frame_for_index(index));
+        }
+        if let Some(index) = frames.clone().find(|&index| self.is_reserved(index)) {
+            panic!("freeing reserved frame {:?}", frame_for_index(index));
+        }
+
+        for index in frames {
+            self.set_used(index, false);
+        }
 
-        frame_addresses.map(|address| PhysFrame::containing_address(PhysAddr::new(address)))
+        self.allocated_frames -= count;
+        self.next = self.next.min(start);
+    }
+
+    fn allocate_huge_frame<S: PageSize>(&mut self) -> Option<PhysFrame<S>> {
+        let frames = (S::SIZE / FRAME_SIZE) as usize;
+        let start = self.allocate_contiguous(frames, frames)?;
+
+        Some(PhysFrame::from_start_address(start.start_address()).unwrap())
+    }
+
+    unsafe fn deallocate_huge_frame<S: PageSize>(&mut self, frame: PhysFrame<S>) {
+        let frames = (S::SIZE / FRAME_SIZE) as usize;
+        self.deallocate_contiguous(PhysFrame::containing_address(frame.start_address()), frames);
+    }
+
+    fn is_reserved(&self, index: usize) -> bool {
+        let start = PhysAddr::new(index as u64 * FRAME_SIZE);
+        let end = start + FRAME_SIZE;
+
+        self.reservations()
+            .any(|reservation| reservation.start < end && start < reservation.end)
+    }
+
+    fn is_used(&self, index: usize) -> bool {
+        self.bitmap[index / BITS_PER_WORD] & (1 << (index % BITS_PER_WORD)) != 0
+    }
+
+    fn set_used(&mut self, index: usize, used: bool) {
+        let word = &mut self.bitmap[index / BITS_PER_WORD];
+        let bit = 1 << (index % BITS_PER_WORD);
+
+        if used {
+            *word |= bit;
+        } else {
+            *word &= !bit;
+        }
+    }
+
+    fn find_free(&self, range: Range<usize>) -> Option<usize> {
+        let mut index = range.start;
+
+        while index < range.end {
+            if self.bitmap[index / BITS_PER_WORD] == !0 {
+                index = align_up(index + 1, BITS_PER_WORD);
+                continue;
+            }
+
+            if !self.is_used(index) {
+                return Some(index);
+            }
+
+            index += 1;
+        }
+
+        None
     }
 }
 
 unsafe impl FrameAllocator<Size4KiB> for PhysicalFrameAllocator {
     fn allocate_frame(&mut self) -> Option<PhysFrame<Size4KiB>> {
-        let frame = self.frames().nth(self.next);
-        self.next += 1;
-        frame
+        let index = self
+            .find_free(self.next..self.frame_count)
+            .or_else(|| self.find_free(0..self.next))?;
+
+        self.set_used(index, true);
+        self.allocated_frames += 1;
+        self.next = index + 1;
+
+        Some(frame_for_index(index))
+    }
+}
+
+impl FrameDeallocator<Size4KiB> for PhysicalFrameAllocator {
+    unsafe fn deallocate_frame(&mut self, frame: PhysFrame<Size4KiB>) {
+        self.deallocate_contiguous(frame, 1);
+    }
+}
+
+unsafe impl FrameAllocator<Size2MiB> for PhysicalFrameAllocator {
+    fn allocate_frame(&mut self) -> Option<PhysFrame<Size2MiB>> {
+        self.allocate_huge_frame()
+    }
+}
+
+impl FrameDeallocator<Size2MiB> for PhysicalFrameAllocator {
+    unsafe fn deallocate_frame(&mut self, frame: PhysFrame<Size2MiB>) {
+        self.deallocate_huge_frame(frame);
+    }
+}
+
+unsafe impl FrameAllocator<Size1GiB> for PhysicalFrameAllocator {
+    fn allocate_frame(&mut self) -> Option<PhysFrame<Size1GiB>> {
+        self.allocate_huge_frame()
+    }
+}
+
+impl FrameDeallocator<Size1GiB> for PhysicalFrameAllocator {
+    unsafe fn deallocate_frame(&mut self, frame: PhysFrame<Size1GiB>) {
+        self.deallocate_huge_frame(frame);
     }
 }
+
+fn align_up(value: usize, alignment: usize) -> usize {
+    (value + alignment - 1) & !(alignment - 1)
+}
+
+fn frame_for_index(index: usize) -> PhysFrame {
+    PhysFrame::containing_address(PhysAddr::new(index as u64 * FRAME_SIZE))
+}
+
+fn index_for_frame(frame: PhysFrame) -> usize {
+    (frame.start_address().as_u64() / FRAME_SIZE) as usize
+}
@@ -0,0 +1,161 @@
+use core::{
+    alloc::{GlobalAlloc, Layout},
+    fmt::Display,
+    ptr::{null_mut, NonNull},
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use linked_list_allocator::Heap;
+use spin::Mutex;
+use x86_64::{
+    instructions::interrupts,
+    structures::paging::{Page, PageTableFlags},
+    VirtAddr,
+};
+
+pub const HEAP_START: u64 = 0x_4444_4444_0000;
+pub const HEAP_INITIAL_SIZE: u64 = 200 * 1024;
+pub const DEFAULT_HEAP_LIMIT: u64 = 64 * 1024 * 1024;
+
+const HEAP_GROWTH_STEP: u64 = 64 * 1024;
+const PAGE_SIZE: u64 = 4096;
+
+static HEAP_LIMIT: AtomicU64 = AtomicU64::new(DEFAULT_HEAP_LIMIT);
+
+/// Sets the maximum size the kernel heap may grow to. Shrinking the limit
+/// below the current heap size stops further growth but doesn't release
+/// anything.
+pub fn set_heap_limit(limit: u64) {
+    HEAP_LIMIT.store(limit, Ordering::Relaxed);
+}
+
+pub fn heap_limit() -> u64 {
+    HEAP_LIMIT.load(Ordering::Relaxed)
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct HeapStats {
+    pub size: usize,
+    pub used: usize,
+    pub free: usize,
+    pub limit: usize,
+}
+
+impl Display for HeapStats {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "heap {} KiB used, {} KiB free, {} KiB mapped of {} KiB limit",
+            self.used / 1024,
+            self.free / 1024,
+            self.size / 1024,
+            self.limit / 1024
+        )
+    }
+}
+
+/// Linked-list heap at `HEAP_START` that maps more pages whenever an
+/// allocation doesn't fit, up to `heap_limit()`.
+pub struct KernelHeap {
+    heap: Mutex<Heap>,
+}
+
+impl KernelHeap {
+    pub const fn new() -> Self {
+        Self {
+            heap: Mutex::new(Heap::empty()),
+        }
+    }
+
+    pub unsafe fn init(&self) {
+        map_heap_pages(HEAP_START, HEAP_INITIAL_SIZE);
+
+        interrupts::without_interrupts(|| {
+            self.heap
+                .lock()
+                .init(HEAP_START as usize, HEAP_INITIAL_SIZE as usize)
+        });
+    }
+
+    pub fn stats(&self) -> HeapStats {
+        interrupts::without_interrupts(|| {
+            let heap = self.heap.lock();
+
+            HeapStats {
+                size: heap.size(),
+                used: heap.used(),
+                free: heap.free(),
+                limit: heap_limit() as usize,
+            }
+        })
+    }
+
+    fn grow(heap: &mut Heap, layout: Layout) -> bool {
+        let size = heap.size() as u64;
+        let wanted = align_up(layout.size() as u64 + layout.align() as u64, PAGE_SIZE);
+        let growth = wanted.max(HEAP_GROWTH_STEP);
+
+        if size + growth > heap_limit() {
+            return false;
+        }
+
+        let free_frames =
+            super::with_frame_allocator(|frame_allocator| frame_allocator.free_frames());
+        if (growth / PAGE_SIZE) as usize > free_frames {
+            return false;
+        }
+
+        unsafe {
+            map_heap_pages(HEAP_START + size, growth);
+            heap.extend(growth as usize);
+        }
+
+        true
+    }
+}
+
+unsafe impl GlobalAlloc for KernelHeap {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        interrupts::without_interrupts(|| {
+            let mut heap = self.heap.lock();
+
+            loop {
+                match heap.allocate_first_fit(layout) {
+                    Ok(ptr) => return ptr.as_ptr(),
+                    Err(()) => {
+                        if !Self::grow(&mut heap, layout) {
+                            return null_mut();
+                        }
+                    }
+                }
+            }
+        })
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        interrupts::without_interrupts(|| {
+            self.heap
+                .lock()
+                .deallocate(NonNull::new_unchecked(ptr), layout)
+        })
+    }
+}
+
+unsafe fn map_heap_pages(start: u64, size: u64) {
+    let start = Page::containing_address(VirtAddr::new(start));
+    let end = Page::containing_address(VirtAddr::new(start.start_address().as_u64() + size - 1));
+
+    for page in Page::range_inclusive(start, end) {
+        super::map_page(
+            page,
+            PageTableFlags::GLOBAL
+                | PageTableFlags::PRESENT
+                | PageTableFlags::NO_EXECUTE
+                | PageTableFlags::WRITABLE,
+        );
+    }
+}
+
+fn align_up(value: u64, alignment: u64) -> u64 {
+    (value + alignment - 1) & !(alignment - 1)
+}
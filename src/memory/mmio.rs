@@ -0,0 +1,225 @@
+use x86_64::{
+    registers::model_specific::Msr,
+    structures::paging::{Page, PageSize, PageTableFlags, PhysFrame, Size1GiB, Size2MiB, Size4KiB},
+    PhysAddr, VirtAddr,
+};
+
+const IA32_PAT: u32 = 0x277;
+
+// PAT entries 0-3 keep their power-on defaults (WB, WT, UC-, UC) so existing
+// mappings are unaffected; entry 4 is repurposed as write-combining.
+const PAT_VALUE: u64 = 0x0007_0401_0007_0406;
+
+// In a level 1 entry bit 7 selects the upper half of the PAT rather than a
+// huge page.
+const PAT_FLAG: PageTableFlags = PageTableFlags::HUGE_PAGE;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CacheMode {
+    WriteBack,
+    WriteThrough,
+    WriteCombining,
+    Uncached,
+}
+
+impl CacheMode {
+    pub fn page_table_flags(&self) -> PageTableFlags {
+        match self {
+            CacheMode::WriteBack => PageTableFlags::empty(),
+            CacheMode::WriteThrough => PageTableFlags::WRITE_THROUGH,
+            CacheMode::WriteCombining => PAT_FLAG,
+            CacheMode::Uncached => PageTableFlags::NO_CACHE | PageTableFlags::WRITE_THROUGH,
+        }
+    }
+}
+
+/// Programs this CPU's PAT; every CPU must agree on it.
+pub(crate) fn init_pat() {
+    unsafe {
+        Msr::new(IA32_PAT).write(PAT_VALUE);
+    }
+}
+
+/// A device memory window mapped into kernel address space. The pages are
+/// unmapped and the address range released when it's dropped; the physical
+/// frames belong to the device and are never handed to the frame allocator.
+///
+/// Large windows are mapped with 2 MiB (or 1 GiB) pages wherever the
+/// physical address allows it, except for write-combining mappings, whose
+/// PAT bit sits in a different place in huge page entries.
+#[derive(Debug)]
+pub struct MmioMapping {
+    physical_start: PhysAddr,
+    virtual_start: VirtAddr,
+    len: usize,
+    range_start: VirtAddr,
+    range_len: u64,
+    pages: MmioPages,
+    cache_mode: CacheMode,
+}
+
+impl MmioMapping {
+    pub(super) fn new(physical_start: PhysAddr, len: usize, cache_mode: CacheMode) -> Self {
+        let first_frame: PhysFrame = PhysFrame::containing_address(physical_start);
+        let offset = physical_start.as_u64() - first_frame.start_address().as_u64();
+        let mapped_len = align_up(offset + len as u64, Size4KiB::SIZE);
+
+        let largest_page = if cache_mode == CacheMode::WriteCombining {
+            Size4KiB::SIZE
+        } else if mapped_len >= Size1GiB::SIZE && super::supports_1gib_pages() {
+            Size1GiB::SIZE
+        } else if mapped_len >= Size2MiB::SIZE {
+            Size2MiB::SIZE
+        } else {
+            Size4KiB::SIZE
+        };
+
+        // Keep the virtual address congruent with the physical one modulo the
+        // largest page size, so huge pages line up on both sides.
+        let range_len = mapped_len + largest_page - Size4KiB::SIZE;
+        let range_start = super::allocate_virtual_range(range_len, largest_page)
+            .expect("Out of kernel virtual address space for MMIO");
+        let mapped_start = range_start + first_frame.start_address().as_u64() % largest_page;
+
+        let pages = MmioPages {
+            virtual_start: mapped_start,
+            physical_start: first_frame.start_address(),
+            len: mapped_len,
+            largest_page,
+        };
+
+        let flags = PageTableFlags::PRESENT
+            | PageTableFlags::WRITABLE
+            | PageTableFlags::NO_EXECUTE
+            | cache_mode.page_table_flags();
+
+        for (virt, phys, size) in pages.iter() {
+            unsafe {
+                if size == Size1GiB::SIZE {
+                    super::map_to::<Size1GiB>(
+                        Page::containing_address(virt),
+                        PhysFrame::containing_address(phys),
+                        flags,
+                    );
+                } else if size == Size2MiB::SIZE {
+                    super::map_to::<Size2MiB>(
+                        Page::containing_address(virt),
+                        PhysFrame::containing_address(phys),
+                        flags,
+                    );
+                } else {
+                    super::map_to::<Size4KiB>(
+                        Page::containing_address(virt),
+                        PhysFrame::containing_address(phys),
+                        flags,
+                    );
+                }
+            }
+        }
+
+        Self {
+            physical_start,
+            virtual_start: mapped_start + offset,
+            len,
+            range_start,
+            range_len,
+            pages,
+            cache_mode,
+        }
+    }
+
+    pub fn physical_start(&self) -> PhysAddr {
+        self.physical_start
+    }
+
+    pub fn virtual_start(&self) -> VirtAddr {
+        self.virtual_start
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn cache_mode(&self) -> CacheMode {
+        self.cache_mode
+    }
+
+    pub fn as_ptr<T>(&self) -> *const T {
+        self.virtual_start.as_ptr()
+    }
+
+    pub fn as_mut_ptr<T>(&self) -> *mut T {
+        self.virtual_start.as_mut_ptr()
+    }
+}
+
+impl Drop for MmioMapping {
+    fn drop(&mut self) {
+        for (virt, _, size) in self.pages.iter() {
+            unsafe {
+                // The mapper takes the PAT bit for a huge page bit and refuses
+                // to unmap the entry, so clear it first. Uncached, so the
+                // device memory is never cacheable in between.
+                if self.cache_mode == CacheMode::WriteCombining {
+                    super::update_flags(
+                        Page::containing_address(virt),
+                        PageTableFlags::PRESENT
+                            | PageTableFlags::NO_EXECUTE
+                            | CacheMode::Uncached.page_table_flags(),
+                    );
+                }
+
+                if size == Size1GiB::SIZE {
+                    super::unmap::<Size1GiB>(Page::containing_address(virt));
+                } else if size == Size2MiB::SIZE {
+                    super::unmap::<Size2MiB>(Page::containing_address(virt));
+                } else {
+                    super::unmap::<Size4KiB>(Page::containing_address(virt));
+                }
+            }
+        }
+
+        super::deallocate_virtual_range(self.range_start, self.range_len);
+    }
+}
+
+/// The page-aligned span of a mapping, split into the largest pages that
+/// fit at each position.
+#[derive(Debug, Copy, Clone)]
+struct MmioPages {
+    virtual_start: VirtAddr,
+    physical_start: PhysAddr,
+    len: u64,
+    largest_page: u64,
+}
+
+impl MmioPages {
+    fn iter(&self) -> impl Iterator<Item = (VirtAddr, PhysAddr, u64)> {
+        let pages = *self;
+        let mut offset = 0;
+
+        core::iter::from_fn(move || {
+            if offset >= pages.len {
+                return None;
+            }
+
+            let physical = pages.physical_start + offset;
+            let remaining = pages.len - offset;
+            let size = [Size1GiB::SIZE, Size2MiB::SIZE, Size4KiB::SIZE]
+                .iter()
+                .copied()
+                .find(|&size| {
+                    size <= pages.largest_page && physical.as_u64() % size == 0 && remaining >= size
+                })
+                .unwrap_or(Size4KiB::SIZE);
+
+            let page = (pages.virtual_start + offset, physical, size);
+            offset += size;
+            Some(page)
+        })
+    }
+}
+
+fn align_up(value: u64, alignment: u64) -> u64 {
+    (value + alignment - 1) & !(alignment - 1)
+}
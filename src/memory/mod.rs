@@ -1,36 +1,78 @@
-use bootloader::BootInfo;
+use alloc::vec::Vec;
+use bootloader::{bootinfo::MemoryMap, BootInfo};
+use core::fmt::Debug;
 use frame_allocator::PhysicalFrameAllocator;
-use linked_list_allocator::LockedHeap;
+use heap::HeapStats;
+use mmio::{CacheMode, MmioMapping};
+use slab::{SlabAllocator, SlabStats};
 use spin::{Mutex, Once};
+use stats::{FrameStats, MemoryStats};
+use virtual_range::VirtualRangeAllocator;
 use x86_64::{
-    structures::paging::FrameAllocator, structures::paging::Mapper,
-    structures::paging::OffsetPageTable, structures::paging::Page, structures::paging::PageTable,
-    structures::paging::PageTableFlags, PhysAddr, VirtAddr,
+    instructions::interrupts, structures::paging::FrameAllocator, structures::paging::Mapper,
+    structures::paging::OffsetPageTable, structures::paging::Page, structures::paging::PageSize,
+    structures::paging::PageTable, structures::paging::PageTableEntry,
+    structures::paging::PageTableFlags, structures::paging::PhysFrame,
+    structures::paging::Size1GiB, structures::paging::Size2MiB, structures::paging::Size4KiB,
+    PhysAddr, VirtAddr,
 };
 
+pub mod dma;
 pub mod frame_allocator;
+pub mod heap;
+pub mod mmio;
+pub mod slab;
+pub mod stack;
+pub mod stats;
+pub mod virtual_range;
 
-pub static HEAP_START: u64 = 0x_4444_4444_0000;
-pub static HEAP_SIZE: u64 = 200 * 1024;
+pub const KERNEL_VIRTUAL_RANGE_START: u64 = 0x_5555_0000_0000;
+pub const KERNEL_VIRTUAL_RANGE_SIZE: u64 = 0x_0100_0000_0000;
 
+static MEMORY_MAP: Once<&'static MemoryMap> = Once::new();
 static mut FRAME_ALLOCATOR: Once<Mutex<PhysicalFrameAllocator>> = Once::new();
 static mut MAPPER: Once<Mutex<OffsetPageTable>> = Once::new();
 static mut PHYSICAL_MEMORY_OFFSET: u64 = 0;
+static VIRTUAL_RANGES: Mutex<VirtualRangeAllocator> = Mutex::new(VirtualRangeAllocator::new());
 
 #[global_allocator]
-static mut GLOBAL_ALLOCATOR: LockedHeap = LockedHeap::empty();
+static GLOBAL_ALLOCATOR: SlabAllocator = SlabAllocator::new();
 
 #[alloc_error_handler]
 fn alloc_error_handler(layout: alloc::alloc::Layout) -> ! {
-    panic!("allocation error: {:?}", layout)
+    panic!(
+        "out of memory allocating {} bytes (align {}): {}",
+        layout.size(),
+        layout.align(),
+        heap_stats()
+    )
 }
 
 pub fn init(boot_info: &'static BootInfo) {
+    unsafe {
+        PHYSICAL_MEMORY_OFFSET = boot_info.physical_memory_offset;
+    }
+
+    MEMORY_MAP.call_once(|| &boot_info.memory_map);
+
+    println!("Initializing frame allocator...");
     unsafe {
         FRAME_ALLOCATOR
             .call_once(|| Mutex::new(PhysicalFrameAllocator::new(&boot_info.memory_map)));
     }
 
+    with_frame_allocator(|frame_allocator| {
+        println!(
+            "{} KiB usable physical memory, {} KiB free",
+            frame_allocator.usable_frames() * 4,
+            frame_allocator.free_frames() * 4
+        )
+    });
+
+    // Application processors start in real mode, so their startup code has
+    // to sit below 1 MiB; set a frame aside before the heap takes them all.
+    crate::smp::reserve_trampoline_frame();
+
     unsafe {
         MAPPER.call_once(|| {
             let physical_memory_offset = VirtAddr::new(boot_info.physical_memory_offset);
@@ -42,15 +84,16 @@ pub fn init(boot_info: &'static BootInfo) {
 
     println!("Intializing kernel heap...");
     unsafe {
-        GLOBAL_ALLOCATOR
-            .lock()
-            .init(HEAP_START as usize, HEAP_SIZE as usize);
+        GLOBAL_ALLOCATOR.init();
     }
 
-    unsafe {
-        PHYSICAL_MEMORY_OFFSET = boot_info.physical_memory_offset;
-    }
+    mmio::init_pat();
+    VIRTUAL_RANGES.lock().init(
+        VirtAddr::new(KERNEL_VIRTUAL_RANGE_START)
+            ..VirtAddr::new(KERNEL_VIRTUAL_RANGE_START + KERNEL_VIRTUAL_RANGE_SIZE),
+    );
 
+    println!("{}", stats());
     println!("Done!");
 }
 
@@ -66,19 +109,271 @@ unsafe fn active_level_4_table(physical_memory_offset: VirtAddr) -> &'static mut
     &mut *page_table_ptr
 }
 
+/// Summarises physical memory, frame allocator, heap and page table usage.
+pub fn stats() -> MemoryStats {
+    let regions = stats::summarise_memory_map(MEMORY_MAP.wait().unwrap());
+
+    let frames = with_frame_allocator(|frame_allocator| FrameStats {
+        usable: frame_allocator.usable_frames(),
+        allocated: frame_allocator.allocated_frames(),
+        reserved: frame_allocator.reserved_frames(),
+        free: frame_allocator.free_frames(),
+    });
+
+    let page_table_frames = with_mapper(|_| {
+        let level_4_table = unsafe { active_level_4_table(VirtAddr::new(PHYSICAL_MEMORY_OFFSET)) };
+        stats::count_page_table_frames(level_4_table)
+    });
+
+    MemoryStats {
+        regions,
+        frames,
+        heap: heap_stats(),
+        slabs: slab_stats().collect::<Vec<_>>(),
+        page_table_frames,
+    }
+}
+
+pub fn heap_stats() -> HeapStats {
+    GLOBAL_ALLOCATOR.heap_stats()
+}
+
+pub fn slab_stats() -> impl Iterator<Item = SlabStats> {
+    GLOBAL_ALLOCATOR.slab_stats()
+}
+
+/// Runs `f` with the frame allocator locked. Interrupts are off while it's
+/// held, since IRQ handlers can allocate and the heap takes it to grow.
+pub fn with_frame_allocator<R>(f: impl FnOnce(&mut PhysicalFrameAllocator) -> R) -> R {
+    interrupts::without_interrupts(|| f(&mut unsafe { FRAME_ALLOCATOR.wait().unwrap() }.lock()))
+}
+
+/// Runs `f` with the page tables locked, with interrupts off for the same
+/// reason as `with_frame_allocator`.
+fn with_mapper<R>(f: impl FnOnce(&mut OffsetPageTable<'static>) -> R) -> R {
+    interrupts::without_interrupts(|| f(&mut unsafe { MAPPER.wait().unwrap() }.lock()))
+}
+
+pub fn allocate_frame() -> Option<PhysFrame> {
+    with_frame_allocator(|frame_allocator| frame_allocator.allocate_frame())
+}
+
+/// Safety: the frame must not be mapped or otherwise in use.
+pub unsafe fn deallocate_frame(frame: PhysFrame) {
+    with_frame_allocator(|frame_allocator| frame_allocator.deallocate_contiguous(frame, 1))
+}
+
 pub unsafe fn map_page(page: Page, flags: PageTableFlags) {
-    let mut mapper = MAPPER.wait().unwrap().lock();
-    let mut frame_allocator = FRAME_ALLOCATOR.wait().unwrap().lock();
+    with_mapper(|mapper| {
+        with_frame_allocator(|frame_allocator| {
+            let frame: PhysFrame = frame_allocator
+                .allocate_frame()
+                .expect("Failed to allocate frame");
+
+            mapper
+                .map_to(page, frame, flags, frame_allocator)
+                .expect("Failed to map page")
+                .flush()
+        })
+    })
+}
+
+/// Maps a freshly allocated 2 MiB frame at `page`.
+pub unsafe fn map_huge_page(page: Page<Size2MiB>, flags: PageTableFlags) {
+    let frame: PhysFrame<Size2MiB> = with_frame_allocator(|frame_allocator| {
+        frame_allocator
+            .allocate_frame()
+            .expect("Failed to allocate 2 MiB frame")
+    });
 
-    let frame = frame_allocator
-        .allocate_frame()
-        .expect("Failed to allocate frame");
+    map_to(page, frame, flags);
+}
+
+pub unsafe fn unmap_huge_page(page: Page<Size2MiB>) {
+    let frame = unmap(page);
+    with_frame_allocator(|frame_allocator| frame_allocator.deallocate_frame(frame));
+}
+
+/// Maps `frame` at `page`, allocating any intermediate page tables. Works
+/// for 4 KiB, 2 MiB and (where `supports_1gib_pages`) 1 GiB pages.
+///
+/// Safety: the caller is responsible for `frame` not being aliased in a way
+/// that breaks memory safety.
+pub unsafe fn map_to<S: PageSize + Debug>(page: Page<S>, frame: PhysFrame<S>, flags: PageTableFlags)
+where
+    OffsetPageTable<'static>: Mapper<S>,
+{
+    with_mapper(|mapper| {
+        with_frame_allocator(|frame_allocator| {
+            mapper
+                .map_to(page, frame, flags, frame_allocator)
+                .expect("Failed to map page")
+                .flush()
+        })
+    })
+}
+
+/// Removes the mapping for `page` and returns the frame it pointed to,
+/// without freeing it. Every CPU's TLB has forgotten the mapping by the time
+/// it returns, so the frame and the address can be reused.
+pub unsafe fn unmap<S: PageSize + Debug>(page: Page<S>) -> PhysFrame<S>
+where
+    OffsetPageTable<'static>: Mapper<S>,
+{
+    let (frame, empty_tables) = with_mapper(|mapper| {
+        let (frame, flush) = mapper.unmap(page).expect("Failed to unmap page");
+        let empty_tables = remove_empty_page_tables(mapper, page.start_address());
+
+        // Also drops any cached entries of the tables just removed.
+        flush.flush();
+        (frame, empty_tables)
+    });
+
+    crate::smp::shootdown(page.start_address(), S::SIZE);
+
+    // Only once no CPU can still be walking through them.
+    for &table in empty_tables.iter().flatten() {
+        deallocate_frame(table);
+    }
 
-    let mapping = mapper
-        .map_to(page, frame, flags, &mut *frame_allocator)
-        .expect("Failed to map page");
+    frame
+}
+
+/// Unlinks the level 1 and 2 tables covering `address` if nothing is mapped
+/// through them any more, so a 2 MiB or 1 GiB page can be mapped over the
+/// same range later, and returns their frames.
+unsafe fn remove_empty_page_tables(
+    mapper: &mut OffsetPageTable,
+    address: VirtAddr,
+) -> [Option<PhysFrame>; 2] {
+    let mut removed = [None, None];
+
+    let level_3_table = match next_table(&mapper.level_4_table()[address.p4_index()]) {
+        Some(table) => table,
+        None => return removed,
+    };
+    let level_3_entry = &mut level_3_table[address.p3_index()];
+    let level_2_table = match next_table(level_3_entry) {
+        Some(table) => table,
+        None => return removed,
+    };
+    let level_2_entry = &mut level_2_table[address.p2_index()];
+
+    if let Some(level_1_table) = next_table(level_2_entry) {
+        removed[0] = remove_if_empty(level_2_entry, level_1_table);
+    }
+    removed[1] = remove_if_empty(level_3_entry, level_2_table);
+
+    removed
+}
+
+/// The table `entry` points to, unless it's empty or maps a huge page.
+unsafe fn next_table(entry: &PageTableEntry) -> Option<&'static mut PageTable> {
+    let flags = entry.flags();
+    if !flags.contains(PageTableFlags::PRESENT) || flags.contains(PageTableFlags::HUGE_PAGE) {
+        return None;
+    }
+
+    Some(&mut *physical_to_virtual_address(entry.addr()).as_mut_ptr())
+}
+
+fn remove_if_empty(entry: &mut PageTableEntry, table: &PageTable) -> Option<PhysFrame> {
+    if !table.iter().all(|entry| entry.is_unused()) {
+        return None;
+    }
+
+    let frame = PhysFrame::containing_address(entry.addr());
+    entry.set_unused();
+    Some(frame)
+}
+
+pub fn supports_1gib_pages() -> bool {
+    let extended_features = unsafe { core::arch::x86_64::__cpuid(0x8000_0001) };
+    extended_features.edx & (1 << 26) != 0
+}
+
+/// Unmaps `page` and returns its frame to the frame allocator; the inverse
+/// of `map_page`.
+pub unsafe fn unmap_page(page: Page) {
+    let frame = unmap(page);
+    deallocate_frame(frame);
+}
+
+/// Replaces the flags on an existing mapping, e.g. to make a page read-only
+/// or non-executable after it's been populated.
+pub unsafe fn update_flags(page: Page, flags: PageTableFlags) {
+    with_mapper(|mapper| {
+        mapper
+            .update_flags(page, flags)
+            .expect("Failed to update page flags")
+            .flush()
+    });
+
+    crate::smp::shootdown(page.start_address(), page.size());
+}
+
+pub fn allocate_virtual_range(size: u64, alignment: u64) -> Option<VirtAddr> {
+    interrupts::without_interrupts(|| VIRTUAL_RANGES.lock().allocate(size, alignment))
+}
+
+pub fn deallocate_virtual_range(start: VirtAddr, size: u64) {
+    interrupts::without_interrupts(|| VIRTUAL_RANGES.lock().deallocate(start, size))
+}
+
+/// Maps `len` bytes of device memory at `physical` with the given caching
+/// behaviour. The mapping is removed when the returned value is dropped.
+pub fn map_mmio(physical: PhysAddr, len: usize, cache_mode: CacheMode) -> MmioMapping {
+    MmioMapping::new(physical, len, cache_mode)
+}
+
+/// Walks the active page tables without taking the mapper lock, so it can be
+/// used from panic and fault handlers. Always false before `init`.
+pub fn is_mapped(address: VirtAddr) -> bool {
+    MEMORY_MAP.r#try().is_some() && walk_page_tables(address).is_some()
+}
+
+/// The physical address `address` is mapped to, if it's mapped at all.
+pub fn translate(address: VirtAddr) -> Option<PhysAddr> {
+    with_mapper(|_| walk_page_tables(address))
+}
+
+/// Bit 7 only marks a huge page in level 3 and 2 entries; in a level 1 entry
+/// it's the PAT bit write-combining mappings set, which the mapper's own
+/// translation mistakes for a huge page.
+fn walk_page_tables(address: VirtAddr) -> Option<PhysAddr> {
+    use x86_64::registers::control::Cr3;
+
+    let indices = [
+        address.p4_index(),
+        address.p3_index(),
+        address.p2_index(),
+        address.p1_index(),
+    ];
+
+    let mut table_address = Cr3::read().0.start_address();
+    for (level, &index) in indices.iter().enumerate() {
+        let table = unsafe { &*physical_to_virtual_address(table_address).as_ptr::<PageTable>() };
+        let entry = &table[index];
+
+        if !entry.flags().contains(PageTableFlags::PRESENT) {
+            return None;
+        }
+
+        // Level 3 and 2 entries can map 1 GiB and 2 MiB pages directly.
+        let huge_page_size = match level {
+            1 => Size1GiB::SIZE,
+            2 => Size2MiB::SIZE,
+            _ => 0,
+        };
+        if huge_page_size != 0 && entry.flags().contains(PageTableFlags::HUGE_PAGE) {
+            let base = entry.addr().as_u64() & !(huge_page_size - 1);
+            return Some(PhysAddr::new(base + address.as_u64() % huge_page_size));
+        }
+
+        table_address = entry.addr();
+    }
 
-    mapping.flush()
+    Some(table_address + address.as_u64() % Size4KiB::SIZE)
 }
 
 pub fn physical_to_virtual_address(physical: PhysAddr) -> VirtAddr {
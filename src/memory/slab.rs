@@ -0,0 +1,187 @@
+use core::{
+    alloc::{GlobalAlloc, Layout},
+    fmt::Display,
+    ptr::null_mut,
+};
+
+use spin::Mutex;
+use x86_64::instructions::interrupts;
+
+use super::{
+    frame_allocator::FRAME_SIZE,
+    heap::{HeapStats, KernelHeap},
+    physical_to_virtual_address,
+};
+
+const SIZE_CLASSES: [usize; 8] = [16, 32, 64, 128, 256, 512, 1024, 2048];
+
+#[derive(Debug, Copy, Clone)]
+pub struct SlabStats {
+    pub object_size: usize,
+    pub objects_in_use: usize,
+    pub pages: usize,
+}
+
+impl Display for SlabStats {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "slab-{}: {} objects in use, {} pages ({} KiB)",
+            self.object_size,
+            self.objects_in_use,
+            self.pages,
+            self.pages * FRAME_SIZE as usize / 1024
+        )
+    }
+}
+
+struct FreeObject {
+    next: *mut FreeObject,
+}
+
+struct SlabState {
+    free_list: *mut FreeObject,
+    objects_in_use: usize,
+    pages: usize,
+}
+
+// The free list only ever points into frames owned by the cache.
+unsafe impl Send for SlabState {}
+
+/// Hands out fixed-size objects carved from whole frames, which are accessed
+/// through the physical memory mapping rather than mapped individually.
+pub struct SlabCache {
+    object_size: usize,
+    state: Mutex<SlabState>,
+}
+
+impl SlabCache {
+    const fn new(object_size: usize) -> Self {
+        Self {
+            object_size,
+            state: Mutex::new(SlabState {
+                free_list: null_mut(),
+                objects_in_use: 0,
+                pages: 0,
+            }),
+        }
+    }
+
+    pub fn stats(&self) -> SlabStats {
+        let state = interrupts::without_interrupts(|| {
+            let state = self.state.lock();
+            (state.objects_in_use, state.pages)
+        });
+
+        SlabStats {
+            object_size: self.object_size,
+            objects_in_use: state.0,
+            pages: state.1,
+        }
+    }
+
+    fn allocate(&self) -> *mut u8 {
+        let mut state = self.state.lock();
+
+        if state.free_list.is_null() && !self.refill(&mut state) {
+            return null_mut();
+        }
+
+        let object = state.free_list;
+        state.free_list = unsafe { (*object).next };
+        state.objects_in_use += 1;
+
+        object as *mut u8
+    }
+
+    unsafe fn deallocate(&self, ptr: *mut u8) {
+        let mut state = self.state.lock();
+
+        let object = ptr as *mut FreeObject;
+        (*object).next = state.free_list;
+        state.free_list = object;
+        state.objects_in_use -= 1;
+    }
+
+    fn refill(&self, state: &mut SlabState) -> bool {
+        let frame = match super::allocate_frame() {
+            Some(frame) => frame,
+            None => return false,
+        };
+
+        let page = physical_to_virtual_address(frame.start_address()).as_mut_ptr::<u8>();
+
+        for index in (0..FRAME_SIZE as usize / self.object_size).rev() {
+            unsafe {
+                let object = page.add(index * self.object_size) as *mut FreeObject;
+                (*object).next = state.free_list;
+                state.free_list = object;
+            }
+        }
+
+        state.pages += 1;
+        true
+    }
+}
+
+/// The kernel's global allocator: small allocations are served from
+/// power-of-two slab caches, anything larger than the biggest size class
+/// falls back to the growable linked-list heap.
+pub struct SlabAllocator {
+    caches: [SlabCache; SIZE_CLASSES.len()],
+    heap: KernelHeap,
+}
+
+impl SlabAllocator {
+    pub const fn new() -> Self {
+        Self {
+            caches: [
+                SlabCache::new(SIZE_CLASSES[0]),
+                SlabCache::new(SIZE_CLASSES[1]),
+                SlabCache::new(SIZE_CLASSES[2]),
+                SlabCache::new(SIZE_CLASSES[3]),
+                SlabCache::new(SIZE_CLASSES[4]),
+                SlabCache::new(SIZE_CLASSES[5]),
+                SlabCache::new(SIZE_CLASSES[6]),
+                SlabCache::new(SIZE_CLASSES[7]),
+            ],
+            heap: KernelHeap::new(),
+        }
+    }
+
+    pub unsafe fn init(&self) {
+        self.heap.init();
+    }
+
+    pub fn heap_stats(&self) -> HeapStats {
+        self.heap.stats()
+    }
+
+    pub fn slab_stats(&self) -> impl Iterator<Item = SlabStats> + '_ {
+        self.caches.iter().map(|cache| cache.stats())
+    }
+
+    fn cache_for(&self, layout: Layout) -> Option<&SlabCache> {
+        // Objects are packed at multiples of the size class from the start
+        // of a frame, so a class at least as large as the alignment is
+        // always suitably aligned.
+        let size = layout.size().max(layout.align());
+        self.caches.iter().find(|cache| cache.object_size >= size)
+    }
+}
+
+unsafe impl GlobalAlloc for SlabAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        match self.cache_for(layout) {
+            Some(cache) => interrupts::without_interrupts(|| cache.allocate()),
+            None => self.heap.alloc(layout),
+        }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        match self.cache_for(layout) {
+            Some(cache) => interrupts::without_interrupts(|| cache.deallocate(ptr)),
+            None => self.heap.dealloc(ptr, layout),
+        }
+    }
+}
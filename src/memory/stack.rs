@@ -0,0 +1,70 @@
+use x86_64::{
+    structures::paging::{Page, PageTableFlags},
+    VirtAddr,
+};
+
+const PAGE_SIZE: u64 = 4096;
+
+/// A kernel stack with an unmapped guard page directly below it, so running
+/// off the bottom faults instead of overwriting whatever comes next.
+#[derive(Debug)]
+pub struct KernelStack {
+    guard_page: Page,
+    pages: u64,
+}
+
+impl KernelStack {
+    pub fn allocate(pages: u64) -> Self {
+        assert!(pages > 0, "kernel stack must have at least one page");
+
+        let start = super::allocate_virtual_range((pages + 1) * PAGE_SIZE, PAGE_SIZE)
+            .expect("Out of kernel virtual address space for stacks");
+        let guard_page = Page::containing_address(start);
+
+        for index in 1..=pages {
+            unsafe {
+                super::map_page(
+                    guard_page + index,
+                    PageTableFlags::PRESENT
+                        | PageTableFlags::WRITABLE
+                        | PageTableFlags::NO_EXECUTE
+                        | PageTableFlags::GLOBAL,
+                );
+            }
+        }
+
+        Self { guard_page, pages }
+    }
+
+    pub fn guard_page(&self) -> Page {
+        self.guard_page
+    }
+
+    pub fn bottom(&self) -> VirtAddr {
+        (self.guard_page + 1).start_address()
+    }
+
+    /// The initial stack pointer; stacks grow down from here.
+    pub fn top(&self) -> VirtAddr {
+        self.bottom() + self.pages * PAGE_SIZE
+    }
+
+    pub fn contains(&self, address: VirtAddr) -> bool {
+        address >= self.guard_page.start_address() && address < self.top()
+    }
+}
+
+impl Drop for KernelStack {
+    fn drop(&mut self) {
+        for index in 1..=self.pages {
+            unsafe {
+                super::unmap_page(self.guard_page + index);
+            }
+        }
+
+        super::deallocate_virtual_range(
+            self.guard_page.start_address(),
+            (self.pages + 1) * PAGE_SIZE,
+        );
+    }
+}
@@ -0,0 +1,99 @@
+use core::fmt::Display;
+
+use alloc::vec::Vec;
+use bootloader::bootinfo::{MemoryMap, MemoryRegionType};
+use x86_64::structures::paging::{PageTable, PageTableFlags};
+
+use super::{frame_allocator::FRAME_SIZE, heap::HeapStats, slab::SlabStats};
+
+#[derive(Debug, Copy, Clone)]
+pub struct FrameStats {
+    pub usable: usize,
+    pub allocated: usize,
+    pub reserved: usize,
+    pub free: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct MemoryStats {
+    /// Total bytes of each region type in the bootloader memory map.
+    pub regions: Vec<(MemoryRegionType, u64)>,
+    pub frames: FrameStats,
+    pub heap: HeapStats,
+    pub slabs: Vec<SlabStats>,
+    /// Frames currently holding page tables for the active address space.
+    pub page_table_frames: usize,
+}
+
+impl Display for MemoryStats {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        writeln!(f, "Memory map:")?;
+        for (region_type, bytes) in &self.regions {
+            writeln!(f, "  {:?}: {} KiB", region_type, bytes / 1024)?;
+        }
+
+        writeln!(
+            f,
+            "Frames: {} usable, {} allocated, {} reserved, {} free ({} KiB)",
+            self.frames.usable,
+            self.frames.allocated,
+            self.frames.reserved,
+            self.frames.free,
+            self.frames.free * FRAME_SIZE as usize / 1024
+        )?;
+        writeln!(f, "Page tables: {} frames", self.page_table_frames)?;
+        writeln!(f, "Heap: {}", self.heap)?;
+
+        write!(f, "Slabs:")?;
+        for slab in &self.slabs {
+            write!(f, "\n  {}", slab)?;
+        }
+
+        Ok(())
+    }
+}
+
+pub(super) fn summarise_memory_map(memory_map: &MemoryMap) -> Vec<(MemoryRegionType, u64)> {
+    let mut regions: Vec<(MemoryRegionType, u64)> = Vec::new();
+
+    for region in memory_map.iter() {
+        let size = region.range.end_addr() - region.range.start_addr();
+
+        match regions
+            .iter_mut()
+            .find(|(region_type, _)| *region_type == region.region_type)
+        {
+            Some((_, total)) => *total += size,
+            None => regions.push((region.region_type, size)),
+        }
+    }
+
+    regions
+}
+
+/// Counts the frames used by the page table hierarchy rooted at
+/// `level_4_table`, including the level 4 table itself.
+pub(super) fn count_page_table_frames(level_4_table: &PageTable) -> usize {
+    count_tables(level_4_table, 4)
+}
+
+fn count_tables(table: &PageTable, level: u8) -> usize {
+    let mut count = 1;
+
+    if level == 1 {
+        return count;
+    }
+
+    for entry in table.iter() {
+        let flags = entry.flags();
+
+        if !flags.contains(PageTableFlags::PRESENT) || flags.contains(PageTableFlags::HUGE_PAGE) {
+            continue;
+        }
+
+        let child = super::physical_to_virtual_address(entry.addr());
+        count += count_tables(unsafe { &*child.as_ptr::<PageTable>() }, level - 1);
+    }
+
+    count
+}
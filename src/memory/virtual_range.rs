@@ -0,0 +1,75 @@
+use core::ops::Range;
+
+use alloc::vec::Vec;
+use x86_64::VirtAddr;
+
+/// First-fit allocator for ranges of kernel virtual address space that
+/// aren't backed by the heap, e.g. MMIO windows and kernel stacks.
+pub struct VirtualRangeAllocator {
+    free: Vec<Range<u64>>,
+}
+
+impl VirtualRangeAllocator {
+    pub const fn new() -> Self {
+        Self { free: Vec::new() }
+    }
+
+    pub fn init(&mut self, range: Range<VirtAddr>) {
+        self.free.clear();
+        self.free.push(range.start.as_u64()..range.end.as_u64());
+    }
+
+    /// Allocates `size` bytes of address space starting at a multiple of
+    /// `alignment`.
+    pub fn allocate(&mut self, size: u64, alignment: u64) -> Option<VirtAddr> {
+        assert!(
+            alignment.is_power_of_two(),
+            "virtual range alignment must be a power of two"
+        );
+
+        let (index, start) = self.free.iter().enumerate().find_map(|(index, range)| {
+            let start = align_up(range.start, alignment);
+            if start + size <= range.end {
+                Some((index, start))
+            } else {
+                None
+            }
+        })?;
+
+        let range = self.free.remove(index);
+        if start + size < range.end {
+            self.free.insert(index, start + size..range.end);
+        }
+        if range.start < start {
+            self.free.insert(index, range.start..start);
+        }
+
+        Some(VirtAddr::new(start))
+    }
+
+    pub fn deallocate(&mut self, start: VirtAddr, size: u64) {
+        let start = start.as_u64();
+        let end = start + size;
+
+        let index = self
+            .free
+            .iter()
+            .position(|range| range.start >= end)
+            .unwrap_or(self.free.len());
+        self.free.insert(index, start..end);
+
+        if index + 1 < self.free.len() && self.free[index + 1].start == end {
+            let next = self.free.remove(index + 1);
+            self.free[index].end = next.end;
+        }
+
+        if index > 0 && self.free[index - 1].end == start {
+            let current = self.free.remove(index);
+            self.free[index - 1].end = current.end;
+        }
+    }
+}
+
+fn align_up(value: u64, alignment: u64) -> u64 {
+    (value + alignment - 1) & !(alignment - 1)
+}
@@ -1,7 +1,10 @@
 use core::panic::PanicInfo;
 
+use crate::backtrace;
+
 pub fn panic_handler(info: &PanicInfo) -> ! {
     println!("{}", info);
+    backtrace::print_backtrace();
     loop {}
 }
 
@@ -9,6 +12,7 @@ pub fn test_panic_handler(info: &PanicInfo) -> ! {
     use crate::qemu;
     serial_println!("[failed]\n");
     serial_println!("Error: {}\n", info);
+    backtrace::print_backtrace();
     qemu::exit_failure();
     loop {}
 }
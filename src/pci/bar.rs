@@ -0,0 +1,14 @@
+use x86_64::PhysAddr;
+
+#[derive(Debug, Copy, Clone)]
+pub enum PciBar {
+    Memory {
+        address: PhysAddr,
+        size: u64,
+        prefetchable: bool,
+    },
+    Io {
+        port: u16,
+        size: u32,
+    },
+}
@@ -1,4 +1,4 @@
-use super::PciDeviceAddress;
+use super::{device_address::SLOTS_PER_BUS, PciDeviceAddress};
 
 pub struct PciBusIterator {
     base_address: u64,
@@ -45,7 +45,7 @@ impl Iterator for PciBusIterator {
 
             if self.function < max_function {
                 self.function += 1;
-            } else if self.slot < 255 {
+            } else if self.slot < SLOTS_PER_BUS - 1 {
                 self.slot += 1;
                 self.function = 0;
             } else {
@@ -0,0 +1,73 @@
+use super::PciDeviceAddress;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[repr(u8)]
+pub enum PciCapabilityId {
+    PowerManagement = 0x01,
+    Msi = 0x05,
+    VendorSpecific = 0x09,
+    PciExpress = 0x10,
+    MsiX = 0x11,
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct PciCapability {
+    pub address: PciDeviceAddress,
+    pub id: u8,
+    pub offset: u8,
+}
+
+impl PciCapability {
+    pub fn read<T: core::fmt::UpperHex + Copy>(&self, offset: u8) -> T {
+        self.address.read_offset(self.offset as u16 + offset as u16)
+    }
+
+    pub fn write<T: core::fmt::UpperHex + Copy>(&self, offset: u8, value: T) {
+        self.address
+            .write_offset(self.offset as u16 + offset as u16, value)
+    }
+}
+
+/// Walks the linked list of capabilities in a function's configuration
+/// space.
+pub struct PciCapabilityIterator {
+    address: PciDeviceAddress,
+    next: u8,
+    remaining: u8,
+}
+
+impl PciCapabilityIterator {
+    pub fn new(address: PciDeviceAddress, first: u8) -> Self {
+        Self {
+            address,
+            next: first,
+            // A well-formed list can't hold more than this many entries, so
+            // stop there rather than looping forever on a broken one.
+            remaining: 48,
+        }
+    }
+}
+
+impl Iterator for PciCapabilityIterator {
+    type Item = PciCapability;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // The bottom two bits are reserved and pointers below 0x40 would
+        // point into the standard header.
+        let offset = self.next & !0b11;
+        if offset < 0x40 || self.remaining == 0 {
+            return None;
+        }
+
+        self.remaining -= 1;
+
+        let id = self.address.read_offset::<u8>(offset as u16);
+        self.next = self.address.read_offset::<u8>(offset as u16 + 1);
+
+        Some(PciCapability {
+            address: self.address,
+            id,
+            offset,
+        })
+    }
+}
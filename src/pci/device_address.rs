@@ -2,9 +2,13 @@ use core::fmt::UpperHex;
 
 use bit_field::BitField;
 use pci::{PciDeviceKind, PciDisplaySubclassKind, PciStorageSubclassKind};
-use x86_64::PhysAddr;
+use x86_64::{PhysAddr, VirtAddr};
 
-use crate::{memory, pci};
+use crate::pci::{self, PciBar, PciCapability, PciCapabilityId, PciCapabilityIterator};
+
+pub const SLOTS_PER_BUS: u8 = 32;
+
+const STATUS_CAPABILITIES_LIST: usize = 4;
 
 pub enum PciBridgeType {
     PciToPciBridge,
@@ -232,22 +236,129 @@ impl PciDeviceAddress {
             );
         }
 
-        let phys_addr = PhysAddr::new(
-            self.base_address
-                + ((self.bus as u64) << 20)
-                + ((self.slot as u64) << 15)
-                + ((self.function as u64) << 12)
-                + (register.offset() as u64),
-        );
+        self.read_offset(register.offset() as u16)
+    }
+
+    pub fn write<T: UpperHex + Copy>(&self, register: PciDeviceRegister, value: T) {
+        if core::mem::size_of::<T>() != register.width().into() {
+            panic!(
+                "Tried to write {} bytes to PCI register {:?} but it's {} bytes wide",
+                core::mem::size_of::<T>(),
+                register,
+                register.width()
+            );
+        }
+
+        self.write_offset(register.offset() as u16, value)
+    }
 
-        let virt_addr = memory::physical_to_virtual_address(phys_addr);
+    /// Reads from an arbitrary offset in the function's configuration space,
+    /// e.g. inside a capability structure.
+    pub fn read_offset<T: UpperHex + Copy>(&self, offset: u16) -> T {
+        let virt_addr = self.config_space_address() + offset as u64;
         let ptr = virt_addr.as_ptr::<T>();
 
-        let value = unsafe { *ptr };
+        let value = unsafe { core::ptr::read_volatile(ptr) };
 
         value
     }
 
+    pub fn write_offset<T: UpperHex + Copy>(&self, offset: u16, value: T) {
+        let virt_addr = self.config_space_address() + offset as u64;
+        let ptr = virt_addr.as_mut_ptr::<T>();
+
+        unsafe { core::ptr::write_volatile(ptr, value) };
+    }
+
+    pub fn capabilities(&self) -> PciCapabilityIterator {
+        let has_capabilities = self
+            .read::<u16>(PciDeviceRegister::Status)
+            .get_bit(STATUS_CAPABILITIES_LIST);
+
+        let first = if has_capabilities {
+            self.read::<u8>(PciDeviceRegister::CapabilitiesPointer)
+        } else {
+            0
+        };
+
+        PciCapabilityIterator::new(*self, first)
+    }
+
+    pub fn find_capability(&self, id: PciCapabilityId) -> Option<PciCapability> {
+        self.capabilities()
+            .find(|capability| capability.id == id as u8)
+    }
+
+    /// Decodes base address register `index`, including its size, or returns
+    /// `None` if the BAR is unused or is the upper half of a 64-bit BAR.
+    pub fn bar(&self, index: u8) -> Option<PciBar> {
+        assert!(index < 6, "invalid BAR index {}", index);
+
+        let offset = 0x10 + 4 * index as u16;
+        let low = self.read_offset::<u32>(offset);
+
+        // Decoding has to be off while the BAR holds all ones, or the
+        // device may briefly claim a bogus address range.
+        let command = self.read::<u16>(PciDeviceRegister::Command);
+        self.write::<u16>(PciDeviceRegister::Command, command & !0b11);
+
+        let bar = if low.get_bit(0) {
+            self.write_offset::<u32>(offset, 0xFFFF_FFFF);
+            let mask = self.read_offset::<u32>(offset) & !0b11;
+            self.write_offset::<u32>(offset, low);
+
+            if mask == 0 {
+                None
+            } else {
+                Some(PciBar::Io {
+                    port: (low & !0b11) as u16,
+                    size: (!mask).wrapping_add(1) & 0xFFFF,
+                })
+            }
+        } else {
+            let is_64_bit = low.get_bits(1..3) == 0b10;
+            let high = if is_64_bit {
+                self.read_offset::<u32>(offset + 4)
+            } else {
+                0
+            };
+
+            self.write_offset::<u32>(offset, 0xFFFF_FFFF);
+            let mut mask = (self.read_offset::<u32>(offset) & !0xF) as u64;
+            self.write_offset::<u32>(offset, low);
+
+            if is_64_bit {
+                self.write_offset::<u32>(offset + 4, 0xFFFF_FFFF);
+                mask |= (self.read_offset::<u32>(offset + 4) as u64) << 32;
+                self.write_offset::<u32>(offset + 4, high);
+            } else {
+                mask |= 0xFFFF_FFFF_0000_0000;
+            }
+
+            if mask & 0xFFFF_FFFF == 0 && !is_64_bit {
+                None
+            } else {
+                Some(PciBar::Memory {
+                    address: PhysAddr::new(((high as u64) << 32) | (low & !0xF) as u64),
+                    size: (!mask).wrapping_add(1),
+                    prefetchable: low.get_bit(3),
+                })
+            }
+        };
+
+        self.write::<u16>(PciDeviceRegister::Command, command);
+
+        bar
+    }
+
+    fn config_space_address(&self) -> VirtAddr {
+        assert!(self.slot < SLOTS_PER_BUS, "invalid PCI slot {}", self.slot);
+
+        pci::bus_config_space(self.base_address, self.bus)
+            + ((self.slot as u64) << 15)
+            + ((self.function as u64) << 12)
+    }
+
     fn slot_root(&self) -> PciDeviceAddress {
         PciDeviceAddress::new(self.base_address, self.segment, self.bus, self.slot, 0)
     }
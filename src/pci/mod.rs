@@ -1,5 +1,8 @@
+mod bar;
 mod bus_iterator;
+mod capability;
 mod device_address;
+pub mod msi;
 mod types;
 
 use core::fmt::UpperHex;
@@ -7,17 +10,28 @@ use core::fmt::UpperHex;
 use crate::{acpi, device};
 use ::acpi::PciConfigRegions;
 use crate::device::DeviceId;
+pub use bar::PciBar;
 use bus_iterator::PciBusIterator;
-pub use device_address::PciDeviceAddress;
-use device_address::{PciDeviceRegister, PciHeaderType};
-use spin::Once;
+pub use capability::*;
+use device_address::PciHeaderType;
+pub use device_address::{PciDeviceAddress, PciDeviceRegister, SLOTS_PER_BUS};
+use hashbrown::HashMap;
+use lazy_static::lazy_static;
+use spin::{Mutex, Once};
 pub use types::*;
-use x86_64::PhysAddr;
+use x86_64::{PhysAddr, VirtAddr};
 
-use crate::memory;
+use crate::memory::{self, mmio::CacheMode, mmio::MmioMapping};
+
+const BUS_CONFIG_SPACE_SIZE: usize = 1 << 20;
 
 static PCI_CONFIG_REGIONS: Once<Option<&'static PciConfigRegions>> = Once::new();
 
+lazy_static! {
+    static ref BUS_CONFIG_SPACES: Mutex<HashMap<(u64, u8), MmioMapping>> =
+        Mutex::new(HashMap::new());
+}
+
 pub fn init() {
     PCI_CONFIG_REGIONS.call_once(|| acpi::pci_config_regions());
 }
@@ -42,6 +56,23 @@ pub fn base_address_for_segment(segment: u16) -> Option<u64> {
     None
 }
 
+/// Returns the virtual address of the memory-mapped configuration space for
+/// `bus`, mapping it uncached the first time it's accessed.
+pub fn bus_config_space(base_address: u64, bus: u8) -> VirtAddr {
+    let mut config_spaces = BUS_CONFIG_SPACES.lock();
+
+    config_spaces
+        .entry((base_address, bus))
+        .or_insert_with(|| {
+            memory::map_mmio(
+                PhysAddr::new(base_address + ((bus as u64) << 20)),
+                BUS_CONFIG_SPACE_SIZE,
+                CacheMode::Uncached,
+            )
+        })
+        .virtual_start()
+}
+
 pub fn discover_pci_bus(
     base_address: u64,
     segment: u16,
@@ -0,0 +1,243 @@
+use alloc::vec::Vec;
+use core::ops::Range;
+
+use bit_field::BitField;
+use x86_64::PhysAddr;
+
+use super::{PciBar, PciCapability, PciCapabilityId, PciDeviceAddress, PciDeviceRegister};
+use crate::{
+    apic,
+    interrupts::irq::{self, irq_vector, IrqRegistration},
+    memory::{self, mmio::CacheMode, mmio::MmioMapping},
+};
+
+const MESSAGE_ADDRESS_BASE: u32 = 0xFEE0_0000;
+
+const COMMAND_INTERRUPT_DISABLE: usize = 10;
+
+const MSI_CONTROL: u8 = 0x02;
+const MSI_ADDRESS: u8 = 0x04;
+const MSI_ADDRESS_HIGH: u8 = 0x08;
+const MSI_DATA_32: u8 = 0x08;
+const MSI_DATA_64: u8 = 0x0C;
+const MSI_ENABLE: usize = 0;
+const MSI_64_BIT: usize = 7;
+
+const MSIX_CONTROL: u8 = 0x02;
+const MSIX_TABLE: u8 = 0x04;
+const MSIX_FUNCTION_MASK: usize = 14;
+const MSIX_ENABLE: usize = 15;
+const MSIX_ENTRY_SIZE: usize = 16;
+const MSIX_ENTRY_MASKED: u32 = 1;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum MsiKind {
+    Msi,
+    MsiX,
+}
+
+/// A block of IRQs delivered to the boot processor as message-signalled
+/// interrupts. Dropping it turns message delivery off again and releases
+/// the IRQs.
+pub struct MsiInterrupts {
+    capability: PciCapability,
+    kind: MsiKind,
+    first_irq: u8,
+    count: u8,
+    table: Option<MmioMapping>,
+    registrations: Vec<IrqRegistration>,
+    // Whether INTx was already disabled, to restore once messages are off.
+    intx_was_disabled: bool,
+}
+
+/// Allocates up to `count` interrupts for the function at `address`,
+/// preferring MSI-X over MSI. Fewer may be granted than asked for; MSI only
+/// deals in powers of two and devices cap how many messages they support.
+///
+/// Returns `None` if the device supports neither, the APICs aren't in use
+/// or there are no free vectors, in which case the driver should fall back
+/// to its INTx line.
+pub fn allocate_interrupts(address: PciDeviceAddress, count: u8) -> Option<MsiInterrupts> {
+    if count == 0 || !apic::is_enabled() {
+        return None;
+    }
+
+    let interrupts = match address.find_capability(PciCapabilityId::MsiX) {
+        Some(capability) => allocate_msix(address, capability, count),
+        None => None,
+    };
+
+    let mut interrupts = interrupts.or_else(|| {
+        address
+            .find_capability(PciCapabilityId::Msi)
+            .and_then(|capability| allocate_msi(capability, count))
+    })?;
+
+    let mut command = address.read::<u16>(PciDeviceRegister::Command);
+    interrupts.intx_was_disabled = command.get_bit(COMMAND_INTERRUPT_DISABLE);
+    command.set_bit(COMMAND_INTERRUPT_DISABLE, true);
+    address.write::<u16>(PciDeviceRegister::Command, command);
+
+    println!(
+        " - {}: {:?} IRQs {:?}",
+        address,
+        interrupts.kind,
+        interrupts.irqs()
+    );
+
+    Some(interrupts)
+}
+
+fn allocate_msi(capability: PciCapability, count: u8) -> Option<MsiInterrupts> {
+    let mut control = capability.read::<u16>(MSI_CONTROL);
+
+    let supported = 1u8 << control.get_bits(1..4).min(5);
+    let count = count.min(supported).next_power_of_two().min(supported);
+    let first_irq = irq::allocate_irqs(count, true)?;
+
+    let is_64_bit = control.get_bit(MSI_64_BIT);
+    capability.write::<u32>(MSI_ADDRESS, message_address());
+    if is_64_bit {
+        capability.write::<u32>(MSI_ADDRESS_HIGH, 0);
+        capability.write::<u16>(MSI_DATA_64, irq_vector(first_irq) as u16);
+    } else {
+        capability.write::<u16>(MSI_DATA_32, irq_vector(first_irq) as u16);
+    }
+
+    control.set_bits(4..7, count.trailing_zeros() as u16);
+    control.set_bit(MSI_ENABLE, true);
+    capability.write::<u16>(MSI_CONTROL, control);
+
+    Some(MsiInterrupts {
+        capability,
+        kind: MsiKind::Msi,
+        first_irq,
+        count,
+        table: None,
+        registrations: register_irqs(first_irq, count),
+        intx_was_disabled: false,
+    })
+}
+
+fn allocate_msix(
+    address: PciDeviceAddress,
+    capability: PciCapability,
+    count: u8,
+) -> Option<MsiInterrupts> {
+    let mut control = capability.read::<u16>(MSIX_CONTROL);
+    let table_size = control.get_bits(0..11) as usize + 1;
+
+    let table_location = capability.read::<u32>(MSIX_TABLE);
+    let bar_address = match address.bar(table_location.get_bits(0..3) as u8) {
+        Some(PciBar::Memory { address, .. }) => address,
+        _ => return None,
+    };
+
+    let count = count.min(table_size.min(u8::MAX as usize) as u8);
+    let first_irq = irq::allocate_irqs(count, false)?;
+
+    let table = memory::map_mmio(
+        PhysAddr::new(bar_address.as_u64() + (table_location & !0b111) as u64),
+        table_size * MSIX_ENTRY_SIZE,
+        CacheMode::Uncached,
+    );
+
+    // Hold off delivery while the table is being filled in.
+    control.set_bit(MSIX_FUNCTION_MASK, true);
+    control.set_bit(MSIX_ENABLE, true);
+    capability.write::<u16>(MSIX_CONTROL, control);
+
+    for entry in 0..table_size {
+        if entry < count as usize {
+            let vector = irq_vector(first_irq + entry as u8) as u32;
+            write_msix_entry(&table, entry, message_address(), vector, 0);
+        } else {
+            write_msix_entry(&table, entry, 0, 0, MSIX_ENTRY_MASKED);
+        }
+    }
+
+    control.set_bit(MSIX_FUNCTION_MASK, false);
+    capability.write::<u16>(MSIX_CONTROL, control);
+
+    Some(MsiInterrupts {
+        capability,
+        kind: MsiKind::MsiX,
+        first_irq,
+        count,
+        table: Some(table),
+        registrations: register_irqs(first_irq, count),
+        intx_was_disabled: false,
+    })
+}
+
+fn write_msix_entry(table: &MmioMapping, entry: usize, address: u32, data: u32, control: u32) {
+    let entry = unsafe { table.as_mut_ptr::<u32>().add(entry * MSIX_ENTRY_SIZE / 4) };
+
+    unsafe {
+        core::ptr::write_volatile(entry.add(3), MSIX_ENTRY_MASKED);
+        core::ptr::write_volatile(entry, address);
+        core::ptr::write_volatile(entry.add(1), 0);
+        core::ptr::write_volatile(entry.add(2), data);
+        core::ptr::write_volatile(entry.add(3), control);
+    }
+}
+
+fn register_irqs(first_irq: u8, count: u8) -> Vec<IrqRegistration> {
+    (first_irq..first_irq + count).map(irq::register).collect()
+}
+
+fn message_address() -> u32 {
+    let destination = apic::local_apic()
+        .expect("MSI requested before the local APIC is up")
+        .id();
+
+    MESSAGE_ADDRESS_BASE | ((destination as u32) << 12)
+}
+
+impl MsiInterrupts {
+    pub fn kind(&self) -> MsiKind {
+        self.kind
+    }
+
+    pub fn irqs(&self) -> Range<u8> {
+        self.first_irq..self.first_irq + self.count
+    }
+
+    pub fn irq(&self, index: u8) -> u8 {
+        assert!(index < self.count, "MSI index {} out of range", index);
+        self.first_irq + index
+    }
+
+    /// Waits for the `index`th interrupt of the block to fire.
+    pub async fn wait(&self, index: u8) {
+        self.registrations[index as usize].wait().await
+    }
+}
+
+impl Drop for MsiInterrupts {
+    fn drop(&mut self) {
+        let (control_offset, enable_bit) = match self.kind {
+            MsiKind::Msi => (MSI_CONTROL, MSI_ENABLE),
+            MsiKind::MsiX => (MSIX_CONTROL, MSIX_ENABLE),
+        };
+
+        let mut control = self.capability.read::<u16>(control_offset);
+        control.set_bit(enable_bit, false);
+        self.capability.write::<u16>(control_offset, control);
+
+        if let Some(table) = &self.table {
+            for entry in 0..self.count as usize {
+                write_msix_entry(table, entry, 0, 0, MSIX_ENTRY_MASKED);
+            }
+        }
+
+        // Lets the driver fall back to its INTx line.
+        let address = self.capability.address;
+        let mut command = address.read::<u16>(PciDeviceRegister::Command);
+        command.set_bit(COMMAND_INTERRUPT_DISABLE, self.intx_was_disabled);
+        address.write::<u16>(PciDeviceRegister::Command, command);
+
+        self.registrations.clear();
+        irq::free_irqs(self.first_irq, self.count);
+    }
+}
@@ -4,6 +4,7 @@ pub enum DeviceKind {
     PciBus,
     PciDevice(PciDeviceKind),
     PcKeyboard,
+    CmosRtc,
 }
 
 #[derive(Debug)]
@@ -30,6 +30,17 @@ pub fn init() {
     }
 }
 
+/// Masks every line on both PICs, for when the APIC takes over.
+pub fn disable() {
+    let mut pic1_data: Port<u8> = Port::new(0x21);
+    let mut pic2_data: Port<u8> = Port::new(0xA1);
+
+    unsafe {
+        pic1_data.write(0xFF);
+        pic2_data.write(0xFF);
+    }
+}
+
 pub fn notify_end_of_interrupt(interrupt_id: u8) {
     unsafe {
         PICS.lock().notify_end_of_interrupt(interrupt_id);
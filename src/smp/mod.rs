@@ -0,0 +1,160 @@
+mod per_cpu;
+mod tlb;
+mod trampoline;
+
+pub use per_cpu::{current_cpu, per_cpu, PerCpu};
+pub use tlb::shootdown;
+pub use trampoline::reserve_trampoline_frame;
+
+use alloc::boxed::Box;
+use core::{
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+    time::Duration,
+};
+
+use ::acpi::ProcessorState;
+use spin::Once;
+use x86_64::instructions::interrupts;
+
+use crate::{
+    acpi, apic, gdt,
+    interrupts::irq::{self, irq_vector, IrqRegistration, IrqResult},
+    memory::{self, stack::KernelStack},
+    task, time,
+};
+use trampoline::Trampoline;
+
+/// The most CPUs that will be brought up; per-CPU tables are sized by this.
+pub const MAX_CPUS: usize = 64;
+
+const AP_STACK_PAGES: u64 = 16;
+
+// Application processors get no device interrupts, so idle ones are woken
+// this often by their local APIC timer to look for work to steal.
+const AP_TICK_FREQUENCY: u64 = 100;
+
+static CPU_COUNT: AtomicUsize = AtomicUsize::new(1);
+static AP_STARTED: AtomicBool = AtomicBool::new(false);
+static AP_TICK_IRQ: Once<IrqRegistration> = Once::new();
+
+/// Sets up the boot processor's per-CPU data and starts every other
+/// processor in the MADT. They wait for `task::init` and then run an
+/// executor each. Needs the APICs and the clock.
+pub fn init() {
+    let local_apic = match apic::local_apic() {
+        Some(local_apic) if apic::is_enabled() => local_apic,
+        _ => {
+            per_cpu::init(0, 0);
+            println!("SMP: no local APIC, using the boot processor only");
+            return;
+        }
+    };
+
+    per_cpu::init(0, local_apic.id());
+
+    let trampoline = match Trampoline::install() {
+        Some(trampoline) => trampoline,
+        None => {
+            println!("SMP: no low memory for the AP trampoline");
+            return;
+        }
+    };
+
+    AP_TICK_IRQ.call_once(|| {
+        let irq = irq::allocate_irqs(1, false).expect("No free IRQ for the AP timer");
+        irq::register_handler(irq, |_| IrqResult::Handled)
+    });
+    tlb::init();
+
+    let processors = acpi::application_processors()
+        .iter()
+        .filter(|processor| processor.state != ProcessorState::Disabled);
+
+    for processor in processors {
+        let cpu = cpu_count();
+        if cpu == MAX_CPUS {
+            println!("SMP: ignoring processors beyond the first {}", MAX_CPUS);
+            break;
+        }
+
+        if start_ap(&trampoline, cpu, processor.local_apic_id) {
+            CPU_COUNT.store(cpu + 1, Ordering::Release);
+        } else {
+            println!(
+                "SMP: processor with APIC ID {} didn't start",
+                processor.local_apic_id
+            );
+        }
+    }
+
+    println!("SMP: {} CPUs online", cpu_count());
+}
+
+/// The number of CPUs running, including the boot processor.
+pub fn cpu_count() -> usize {
+    CPU_COUNT.load(Ordering::Acquire)
+}
+
+fn start_ap(trampoline: &Trampoline, cpu: usize, apic_id: u8) -> bool {
+    let local_apic = apic::local_apic().unwrap();
+
+    // Never freed: the processor runs on it for good.
+    let stack = Box::leak(Box::new(KernelStack::allocate(AP_STACK_PAGES)));
+    trampoline.set_parameters(stack.top(), ap_entry, cpu as u64);
+    AP_STARTED.store(false, Ordering::SeqCst);
+
+    local_apic.send_init_ipi(apic_id);
+    wait_until(Duration::from_millis(10), || false);
+
+    // The second startup IPI is for processors that miss the first; one
+    // that's already running ignores it.
+    for _ in 0..2 {
+        local_apic.send_startup_ipi(apic_id, trampoline.startup_page());
+        if wait_until(Duration::from_micros(200), started) {
+            return true;
+        }
+    }
+
+    wait_until(Duration::from_millis(100), started)
+}
+
+fn started() -> bool {
+    AP_STARTED.load(Ordering::SeqCst)
+}
+
+/// Spins until `condition` holds or `timeout` passes.
+fn wait_until(timeout: Duration, condition: impl Fn() -> bool) -> bool {
+    let deadline = time::now() + timeout;
+
+    while time::now() < deadline {
+        if condition() {
+            return true;
+        }
+        core::sync::atomic::spin_loop_hint();
+    }
+
+    condition()
+}
+
+/// Where application processors land in long mode, on their own stack.
+extern "C" fn ap_entry(cpu: u64) -> ! {
+    let cpu = cpu as usize;
+    let local_apic = apic::local_apic().unwrap();
+
+    per_cpu::init(cpu, local_apic.id());
+    memory::mmio::init_pat();
+    gdt::init_ap();
+    crate::interrupts::init();
+    apic::init_ap();
+
+    if let (Some(frequency), Some(tick)) = (apic::timer_frequency(), AP_TICK_IRQ.r#try()) {
+        let count = (frequency / AP_TICK_FREQUENCY) as u32;
+        local_apic.start_timer(count, Some(irq_vector(tick.irq())), true);
+    }
+
+    println!("SMP: CPU {} (APIC ID {}) started", cpu, local_apic.id());
+    AP_STARTED.store(true, Ordering::SeqCst);
+
+    interrupts::enable();
+    task::run_ap(cpu)
+}
@@ -0,0 +1,73 @@
+use alloc::boxed::Box;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use x86_64::registers::model_specific::Msr;
+
+const IA32_GS_BASE: u32 = 0xC000_0101;
+
+/// Data private to one CPU, found through its GS base.
+#[repr(C)]
+pub struct PerCpu {
+    // Read through `gs:[0]` and `gs:[8]`, so these have to come first.
+    this: *const PerCpu,
+    index: usize,
+    apic_id: u8,
+}
+
+unsafe impl Sync for PerCpu {}
+
+impl PerCpu {
+    /// Numbered from zero, with the boot processor first.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    pub fn apic_id(&self) -> u8 {
+        self.apic_id
+    }
+}
+
+// Until the boot processor has set its GS base every CPU counts as CPU 0.
+static READY: AtomicBool = AtomicBool::new(false);
+
+/// Points the calling CPU's GS base at a fresh `PerCpu`.
+pub(super) fn init(index: usize, apic_id: u8) {
+    let per_cpu = Box::leak(Box::new(PerCpu {
+        this: core::ptr::null(),
+        index,
+        apic_id,
+    }));
+    let this = per_cpu as *const PerCpu;
+    per_cpu.this = this;
+
+    unsafe {
+        Msr::new(IA32_GS_BASE).write(this as u64);
+    }
+
+    READY.store(true, Ordering::Release);
+}
+
+pub fn per_cpu() -> Option<&'static PerCpu> {
+    if !READY.load(Ordering::Acquire) {
+        return None;
+    }
+
+    let this: *const PerCpu;
+    unsafe {
+        asm!("mov {}, gs:[0]", out(reg) this, options(nostack, readonly, preserves_flags));
+        Some(&*this)
+    }
+}
+
+/// The index of the CPU this is running on.
+pub fn current_cpu() -> usize {
+    if !READY.load(Ordering::Acquire) {
+        return 0;
+    }
+
+    let index: usize;
+    unsafe {
+        asm!("mov {}, gs:[8]", out(reg) index, options(nostack, readonly, preserves_flags));
+    }
+    index
+}
@@ -0,0 +1,98 @@
+use core::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+use spin::{Mutex, MutexGuard, Once};
+use x86_64::{instructions::tlb, VirtAddr};
+
+use super::{cpu_count, current_cpu, MAX_CPUS};
+use crate::{
+    apic,
+    interrupts::irq::{self, irq_vector, IrqRegistration, IrqResult},
+};
+
+// Past this many pages it's cheaper to flush the whole TLB.
+const MAX_INVLPG_PAGES: u64 = 32;
+
+static SHOOTDOWN_IRQ: Once<IrqRegistration> = Once::new();
+
+// Held by the CPU whose shootdown is in flight; the range is only written
+// under it.
+static SHOOTDOWN: Mutex<()> = Mutex::new(());
+static RANGE_START: AtomicU64 = AtomicU64::new(0);
+static RANGE_SIZE: AtomicU64 = AtomicU64::new(0);
+
+// Set for each CPU that has yet to flush the range.
+static PENDING: [AtomicBool; MAX_CPUS] = [AtomicBool::new(false); MAX_CPUS];
+
+pub(super) fn init() {
+    SHOOTDOWN_IRQ.call_once(|| {
+        let irq = irq::allocate_irqs(1, false).expect("No free IRQ for TLB shootdowns");
+        irq::register_handler(irq, |_| {
+            flush_pending();
+            IrqResult::Handled
+        })
+    });
+}
+
+/// Flushes `size` bytes of mappings from `start` out of every other CPU's
+/// TLB, and waits until they all have. The caller flushes its own. Called
+/// after a mapping is removed or restricted, before the memory behind it
+/// is reused.
+pub fn shootdown(start: VirtAddr, size: u64) {
+    let irq = match SHOOTDOWN_IRQ.r#try() {
+        Some(irq) if cpu_count() > 1 => irq.irq(),
+        _ => return,
+    };
+
+    let _guard = lock();
+
+    RANGE_START.store(start.as_u64(), Ordering::Relaxed);
+    RANGE_SIZE.store(size, Ordering::Relaxed);
+
+    let this_cpu = current_cpu();
+    let others = || (0..cpu_count()).filter(move |&cpu| cpu != this_cpu);
+    for cpu in others() {
+        PENDING[cpu].store(true, Ordering::Release);
+    }
+
+    apic::local_apic()
+        .expect("SMP without a local APIC")
+        .send_ipi_to_others(irq_vector(irq));
+
+    while others().any(|cpu| PENDING[cpu].load(Ordering::Acquire)) {
+        core::sync::atomic::spin_loop_hint();
+    }
+}
+
+/// Takes the shootdown lock, flushing for whoever holds it in the meantime;
+/// they may be waiting on this CPU, which could have interrupts disabled.
+fn lock() -> MutexGuard<'static, ()> {
+    loop {
+        if let Some(guard) = SHOOTDOWN.try_lock() {
+            return guard;
+        }
+
+        flush_pending();
+        core::sync::atomic::spin_loop_hint();
+    }
+}
+
+fn flush_pending() {
+    let pending = &PENDING[current_cpu()];
+    if !pending.load(Ordering::Acquire) {
+        return;
+    }
+
+    let start = VirtAddr::new(RANGE_START.load(Ordering::Relaxed));
+    let size = RANGE_SIZE.load(Ordering::Relaxed);
+    let pages = (size + 4095) / 4096;
+
+    if pages > MAX_INVLPG_PAGES {
+        tlb::flush_all();
+    } else {
+        for page in 0..pages {
+            tlb::flush(start + page * 4096);
+        }
+    }
+
+    pending.store(false, Ordering::Release);
+}
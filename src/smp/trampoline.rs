@@ -0,0 +1,211 @@
+use spin::Once;
+use x86_64::{
+    registers::control::Cr3,
+    structures::paging::{Page, PageTableFlags, PhysFrame},
+    PhysAddr, VirtAddr,
+};
+
+use crate::memory;
+
+const LOW_MEMORY_END: u64 = 0x10_0000;
+
+// Entered in real mode at offset 0 of the page named by the startup IPI,
+// with CS pointing at that page. Goes straight to long mode on the kernel's
+// page tables, which is why the page also has to be identity mapped, then
+// calls the entry point on the stack it's been given. Anything that needs
+// the page's linear address is patched in by `Trampoline::install`. CR0 is
+// loaded outright, not amended, as INIT leaves the caches disabled in it.
+global_asm!(
+    r#"
+.intel_syntax noprefix
+.global ap_trampoline_start
+.global ap_trampoline_long_mode
+.global ap_trampoline_gdt
+.global ap_trampoline_gdt_pointer
+.global ap_trampoline_far_pointer
+.global ap_trampoline_parameters
+.global ap_trampoline_end
+
+.p2align 3
+.code16
+ap_trampoline_start:
+    cli
+    cld
+    mov ax, cs
+    mov ds, ax
+
+    mov eax, cr4
+    or eax, 0xA0
+    mov cr4, eax
+
+    mov eax, dword ptr [ap_trampoline_parameters - ap_trampoline_start]
+    mov cr3, eax
+
+    mov ecx, 0xC0000080
+    rdmsr
+    or eax, 0x900
+    wrmsr
+
+    lgdt [ap_trampoline_gdt_pointer - ap_trampoline_start]
+
+    mov eax, 0x80010033
+    mov cr0, eax
+
+    jmp fword ptr [ap_trampoline_far_pointer - ap_trampoline_start]
+
+.code64
+ap_trampoline_long_mode:
+    xor ax, ax
+    mov ds, ax
+    mov es, ax
+    mov ss, ax
+
+    mov rsp, [rip + ap_trampoline_parameters + 8]
+    mov rdi, [rip + ap_trampoline_parameters + 24]
+    xor rbp, rbp
+    call [rip + ap_trampoline_parameters + 16]
+    ud2
+
+.p2align 3
+ap_trampoline_gdt:
+    .quad 0
+    .quad 0x00209A0000000000
+ap_trampoline_gdt_pointer:
+    .word ap_trampoline_gdt_pointer - ap_trampoline_gdt - 1
+    .long 0
+ap_trampoline_far_pointer:
+    .long 0
+    .word 0x08
+
+.p2align 3
+ap_trampoline_parameters:
+    .quad 0
+    .quad 0
+    .quad 0
+    .quad 0
+ap_trampoline_end:
+.att_syntax
+"#
+);
+
+extern "C" {
+    static ap_trampoline_start: u8;
+    static ap_trampoline_long_mode: u8;
+    static ap_trampoline_gdt: u8;
+    static ap_trampoline_gdt_pointer: u8;
+    static ap_trampoline_far_pointer: u8;
+    static ap_trampoline_parameters: u8;
+    static ap_trampoline_end: u8;
+}
+
+/// Read by the trampoline; the layout must match `ap_trampoline_parameters`.
+#[repr(C)]
+struct Parameters {
+    cr3: u64,
+    stack: u64,
+    entry: u64,
+    argument: u64,
+}
+
+static FRAME: Once<Option<PhysFrame>> = Once::new();
+
+pub fn reserve_trampoline_frame() {
+    FRAME.call_once(|| {
+        memory::with_frame_allocator(|frame_allocator| {
+            frame_allocator.allocate_contiguous_below(1, 1, PhysAddr::new(LOW_MEMORY_END))
+        })
+    });
+}
+
+/// Offset of a trampoline symbol from the start of the trampoline.
+fn offset(symbol: &u8) -> usize {
+    symbol as *const u8 as usize - unsafe { &ap_trampoline_start as *const u8 as usize }
+}
+
+/// The trampoline copied into its low memory frame, identity mapped until
+/// it's dropped.
+pub struct Trampoline {
+    frame: PhysFrame,
+    mapped: bool,
+}
+
+impl Trampoline {
+    pub fn install() -> Option<Trampoline> {
+        let frame = (*FRAME.r#try()?)?;
+        let base = frame.start_address().as_u64();
+
+        let len = unsafe { offset(&ap_trampoline_end) };
+        assert!(len <= 4096, "AP trampoline doesn't fit in a page");
+
+        let copy = memory::physical_to_virtual_address(frame.start_address()).as_mut_ptr::<u8>();
+        unsafe {
+            core::ptr::copy_nonoverlapping(&ap_trampoline_start as *const u8, copy, len);
+
+            let gdt_base = copy
+                .add(offset(&ap_trampoline_gdt_pointer) + 2)
+                .cast::<u32>();
+            gdt_base.write_unaligned((base + offset(&ap_trampoline_gdt) as u64) as u32);
+
+            let long_mode = copy.add(offset(&ap_trampoline_far_pointer)).cast::<u32>();
+            long_mode.write_unaligned((base + offset(&ap_trampoline_long_mode) as u64) as u32);
+        }
+
+        let page = Page::containing_address(VirtAddr::new(base));
+        let mapped = match memory::translate(page.start_address()) {
+            Some(address) if address == frame.start_address() => false,
+            Some(address) => panic!(
+                "AP trampoline page {:?} is already mapped to {:?}",
+                page, address
+            ),
+            None => {
+                unsafe { memory::map_to(page, frame, PageTableFlags::PRESENT) };
+                true
+            }
+        };
+
+        Some(Trampoline { frame, mapped })
+    }
+
+    /// The startup IPI vector: the page number the trampoline starts at.
+    pub fn startup_page(&self) -> u8 {
+        (self.frame.start_address().as_u64() / 4096) as u8
+    }
+
+    /// Sets what the next processor started through the trampoline runs.
+    pub fn set_parameters(
+        &self,
+        stack_top: VirtAddr,
+        entry: extern "C" fn(u64) -> !,
+        argument: u64,
+    ) {
+        let cr3 = Cr3::read().0.start_address().as_u64();
+        assert!(
+            cr3 < 1 << 32,
+            "page tables must be below 4 GiB to start APs"
+        );
+
+        let parameters = Parameters {
+            cr3,
+            stack: stack_top.as_u64(),
+            entry: entry as usize as u64,
+            argument,
+        };
+
+        unsafe {
+            let copy = memory::physical_to_virtual_address(self.frame.start_address())
+                .as_mut_ptr::<u8>()
+                .add(offset(&ap_trampoline_parameters))
+                .cast::<Parameters>();
+            core::ptr::write_volatile(copy, parameters);
+        }
+    }
+}
+
+impl Drop for Trampoline {
+    fn drop(&mut self) {
+        if self.mapped {
+            let page = Page::containing_address(VirtAddr::new(self.frame.start_address().as_u64()));
+            unsafe { memory::unmap(page) };
+        }
+    }
+}
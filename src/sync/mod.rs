@@ -0,0 +1,16 @@
+//! Async synchronisation primitives. Unlike `spin`'s locks these suspend the
+//! waiting task rather than spinning, so they're safe to hold across
+//! `.await`.
+
+pub mod mpsc;
+pub mod oneshot;
+
+mod mutex;
+mod notify;
+mod rw_lock;
+mod semaphore;
+
+pub use mutex::{Mutex, MutexGuard};
+pub use notify::{Notified, Notify};
+pub use rw_lock::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+pub use semaphore::{Acquire, AcquireError, Semaphore, SemaphorePermit};
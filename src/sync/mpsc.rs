@@ -0,0 +1,288 @@
+use alloc::{collections::VecDeque, sync::Arc};
+use core::{
+    fmt::{Debug, Display},
+    task::{Context, Poll, Waker},
+};
+
+use futures_util::future::poll_fn;
+use spin::Mutex;
+use x86_64::instructions::interrupts;
+
+use super::Semaphore;
+use crate::task::{self, WaitReason};
+
+/// The receiver is gone; the value that couldn't be sent is handed back.
+pub struct SendError<T>(pub T);
+
+impl<T> Debug for SendError<T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "SendError(..)")
+    }
+}
+
+impl<T> Display for SendError<T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "channel closed")
+    }
+}
+
+pub enum TrySendError<T> {
+    Full(T),
+    Closed(T),
+}
+
+impl<T> Debug for TrySendError<T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            TrySendError::Full(_) => write!(f, "Full(..)"),
+            TrySendError::Closed(_) => write!(f, "Closed(..)"),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TryRecvError {
+    Empty,
+    Disconnected,
+}
+
+struct ChanState<T> {
+    queue: VecDeque<T>,
+    senders: usize,
+    receiver_alive: bool,
+    receiver_waker: Option<Waker>,
+}
+
+struct Chan<T> {
+    state: Mutex<ChanState<T>>,
+    // One permit per free slot; `None` for unbounded channels.
+    slots: Option<Semaphore>,
+}
+
+impl<T> Chan<T> {
+    fn new(slots: Option<Semaphore>) -> Arc<Self> {
+        Arc::new(Self {
+            state: Mutex::new(ChanState {
+                queue: VecDeque::new(),
+                senders: 1,
+                receiver_alive: true,
+                receiver_waker: None,
+            }),
+            slots,
+        })
+    }
+
+    fn with_state<R>(&self, f: impl FnOnce(&mut ChanState<T>) -> R) -> R {
+        interrupts::without_interrupts(|| f(&mut self.state.lock()))
+    }
+
+    fn push(&self, value: T) -> Result<(), T> {
+        let waker = self.with_state(|state| {
+            if !state.receiver_alive {
+                return Err(value);
+            }
+
+            state.queue.push_back(value);
+            Ok(state.receiver_waker.take())
+        })?;
+
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+
+        Ok(())
+    }
+
+    fn add_sender(&self) {
+        self.with_state(|state| state.senders += 1);
+    }
+
+    fn remove_sender(&self) {
+        let waker = self.with_state(|state| {
+            state.senders -= 1;
+            if state.senders == 0 {
+                state.receiver_waker.take()
+            } else {
+                None
+            }
+        });
+
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+}
+
+/// Creates a channel that holds at most `capacity` values; `Sender::send`
+/// waits for room once it's full.
+pub fn channel<T>(capacity: usize) -> (Sender<T>, Receiver<T>) {
+    assert!(capacity > 0, "channel capacity must be at least 1");
+
+    let chan = Chan::new(Some(Semaphore::new(capacity)));
+    (Sender { chan: chan.clone() }, Receiver { chan })
+}
+
+/// Creates a channel with no limit on how many values it buffers.
+pub fn unbounded_channel<T>() -> (UnboundedSender<T>, Receiver<T>) {
+    let chan = Chan::new(None);
+    (UnboundedSender { chan: chan.clone() }, Receiver { chan })
+}
+
+pub struct Sender<T> {
+    chan: Arc<Chan<T>>,
+}
+
+impl<T> Sender<T> {
+    pub async fn send(&self, value: T) -> Result<(), SendError<T>> {
+        let slots = self.chan.slots.as_ref().unwrap();
+        match slots.acquire().await {
+            Ok(permit) => permit.forget(),
+            Err(_) => return Err(SendError(value)),
+        }
+
+        self.chan.push(value).map_err(|value| {
+            slots.add_permits(1);
+            SendError(value)
+        })
+    }
+
+    pub fn try_send(&self, value: T) -> Result<(), TrySendError<T>> {
+        let slots = self.chan.slots.as_ref().unwrap();
+        match slots.try_acquire() {
+            Some(permit) => permit.forget(),
+            None if slots.is_closed() => return Err(TrySendError::Closed(value)),
+            None => return Err(TrySendError::Full(value)),
+        }
+
+        self.chan.push(value).map_err(|value| {
+            slots.add_permits(1);
+            TrySendError::Closed(value)
+        })
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.chan.with_state(|state| !state.receiver_alive)
+    }
+}
+
+impl<T> Clone for Sender<T> {
+    fn clone(&self) -> Self {
+        self.chan.add_sender();
+        Self {
+            chan: self.chan.clone(),
+        }
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        self.chan.remove_sender();
+    }
+}
+
+pub struct UnboundedSender<T> {
+    chan: Arc<Chan<T>>,
+}
+
+impl<T> UnboundedSender<T> {
+    /// Never waits, so it can be used outside of a task.
+    pub fn send(&self, value: T) -> Result<(), SendError<T>> {
+        self.chan.push(value).map_err(SendError)
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.chan.with_state(|state| !state.receiver_alive)
+    }
+}
+
+impl<T> Clone for UnboundedSender<T> {
+    fn clone(&self) -> Self {
+        self.chan.add_sender();
+        Self {
+            chan: self.chan.clone(),
+        }
+    }
+}
+
+impl<T> Drop for UnboundedSender<T> {
+    fn drop(&mut self) {
+        self.chan.remove_sender();
+    }
+}
+
+pub struct Receiver<T> {
+    chan: Arc<Chan<T>>,
+}
+
+impl<T> Receiver<T> {
+    /// Waits for the next value, or returns `None` once every sender has
+    /// been dropped or the receiver closed, and the buffer is empty.
+    pub async fn recv(&mut self) -> Option<T> {
+        poll_fn(|cx| self.poll_recv(cx)).await
+    }
+
+    pub fn poll_recv(&mut self, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        match self.try_recv() {
+            Ok(value) => return Poll::Ready(Some(value)),
+            Err(TryRecvError::Disconnected) => return Poll::Ready(None),
+            Err(TryRecvError::Empty) => {}
+        }
+
+        // Check again with the waker registered, in case a value or the last
+        // sender's drop slipped in between.
+        let registered = self.chan.with_state(|state| {
+            if state.queue.is_empty() && state.senders > 0 && state.receiver_alive {
+                state.receiver_waker = Some(cx.waker().clone());
+                true
+            } else {
+                false
+            }
+        });
+
+        if registered {
+            task::set_wait_reason(WaitReason::Other("channel"));
+            Poll::Pending
+        } else {
+            self.poll_recv(cx)
+        }
+    }
+
+    pub fn try_recv(&mut self) -> Result<T, TryRecvError> {
+        let value = self
+            .chan
+            .with_state(|state| match state.queue.pop_front() {
+                Some(value) => Ok(value),
+                None if state.senders == 0 || !state.receiver_alive => {
+                    Err(TryRecvError::Disconnected)
+                }
+                None => Err(TryRecvError::Empty),
+            })?;
+
+        if let Some(slots) = &self.chan.slots {
+            slots.add_permits(1);
+        }
+
+        Ok(value)
+    }
+
+    /// Stops accepting new values; those already buffered can still be
+    /// received, after which the channel reports itself disconnected.
+    pub fn close(&mut self) {
+        self.chan.with_state(|state| state.receiver_alive = false);
+        if let Some(slots) = &self.chan.slots {
+            slots.close();
+        }
+    }
+}
+
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        self.close();
+
+        // Drop whatever's left outside of the lock.
+        let remaining = self
+            .chan
+            .with_state(|state| core::mem::take(&mut state.queue));
+        drop(remaining);
+    }
+}
@@ -0,0 +1,153 @@
+use alloc::{sync::Arc, vec::Vec};
+use core::{
+    future::Future,
+    pin::Pin,
+    sync::atomic::{AtomicU8, Ordering},
+    task::{Context, Poll},
+};
+
+use futures_util::task::AtomicWaker;
+use spin::Mutex;
+use x86_64::instructions::interrupts;
+
+use crate::task::{self, WaitReason};
+
+// How a waiter was woken, if it has been. Only a `notify_one` wakeup is
+// passed on if the waiter goes away without seeing it.
+const WAITING: u8 = 0;
+const NOTIFIED_ONE: u8 = 1;
+const NOTIFIED_ALL: u8 = 2;
+
+struct Waiter {
+    notified: AtomicU8,
+    waker: AtomicWaker,
+}
+
+impl Waiter {
+    fn notify(&self, how: u8) {
+        self.notified.store(how, Ordering::Release);
+        self.waker.wake();
+    }
+}
+
+struct NotifyState {
+    permit: bool,
+    waiters: Vec<Arc<Waiter>>,
+}
+
+/// Wakes tasks waiting for an event without carrying any data. A
+/// `notify_one` with nobody waiting is remembered, so the next `notified`
+/// completes immediately. Safe to notify from interrupt handlers.
+pub struct Notify {
+    state: Mutex<NotifyState>,
+}
+
+impl Notify {
+    pub const fn new() -> Self {
+        Self {
+            state: Mutex::new(NotifyState {
+                permit: false,
+                waiters: Vec::new(),
+            }),
+        }
+    }
+
+    fn with_state<R>(&self, f: impl FnOnce(&mut NotifyState) -> R) -> R {
+        interrupts::without_interrupts(|| f(&mut self.state.lock()))
+    }
+
+    pub fn notified(&self) -> Notified<'_> {
+        Notified {
+            notify: self,
+            waiter: None,
+        }
+    }
+
+    /// Wakes the longest waiting task, or stores a permit for the next one.
+    pub fn notify_one(&self) {
+        self.with_state(NotifyState::notify_one);
+    }
+
+    /// Wakes every task currently waiting without storing a permit.
+    pub fn notify_waiters(&self) {
+        self.with_state(|state| {
+            for waiter in state.waiters.drain(..) {
+                waiter.notify(NOTIFIED_ALL);
+            }
+        });
+    }
+}
+
+impl NotifyState {
+    fn notify_one(&mut self) {
+        if self.waiters.is_empty() {
+            self.permit = true;
+            return;
+        }
+
+        self.waiters.remove(0).notify(NOTIFIED_ONE);
+    }
+}
+
+pub struct Notified<'a> {
+    notify: &'a Notify,
+    waiter: Option<Arc<Waiter>>,
+}
+
+impl Future for Notified<'_> {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        if let Some(waiter) = &self.waiter {
+            waiter.waker.register(cx.waker());
+
+            if waiter.notified.load(Ordering::Acquire) != WAITING {
+                self.waiter = None;
+                return Poll::Ready(());
+            }
+        } else {
+            let waiter = self.notify.with_state(|state| {
+                if state.permit {
+                    state.permit = false;
+                    return None;
+                }
+
+                let waiter = Arc::new(Waiter {
+                    notified: AtomicU8::new(WAITING),
+                    waker: AtomicWaker::new(),
+                });
+                waiter.waker.register(cx.waker());
+                state.waiters.push(waiter.clone());
+
+                Some(waiter)
+            });
+
+            match waiter {
+                Some(waiter) => self.waiter = Some(waiter),
+                None => return Poll::Ready(()),
+            }
+        }
+
+        task::set_wait_reason(WaitReason::Other("notify"));
+        Poll::Pending
+    }
+}
+
+impl Drop for Notified<'_> {
+    fn drop(&mut self) {
+        let waiter = match self.waiter.take() {
+            Some(waiter) => waiter,
+            None => return,
+        };
+
+        self.notify.with_state(|state| {
+            match waiter.notified.load(Ordering::Acquire) {
+                WAITING => state.waiters.retain(|other| !Arc::ptr_eq(other, &waiter)),
+                // Pass on a notification that arrived after we stopped
+                // listening, so it isn't lost.
+                NOTIFIED_ONE => state.notify_one(),
+                _ => {}
+            }
+        });
+    }
+}
@@ -0,0 +1,140 @@
+use alloc::sync::Arc;
+use core::{
+    fmt::Display,
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll, Waker},
+};
+
+use spin::Mutex;
+use x86_64::instructions::interrupts;
+
+use crate::task::{self, WaitReason};
+
+/// The sender was dropped without sending a value.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct RecvError;
+
+impl Display for RecvError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "sender dropped")
+    }
+}
+
+struct OneshotState<T> {
+    value: Option<T>,
+    sender_alive: bool,
+    receiver_alive: bool,
+    receiver_waker: Option<Waker>,
+}
+
+type Shared<T> = Arc<Mutex<OneshotState<T>>>;
+
+fn with_state<T, R>(shared: &Shared<T>, f: impl FnOnce(&mut OneshotState<T>) -> R) -> R {
+    interrupts::without_interrupts(|| f(&mut shared.lock()))
+}
+
+/// Creates a channel for sending a single value, e.g. a reply to a request
+/// passed over an `mpsc` channel.
+pub fn channel<T>() -> (Sender<T>, Receiver<T>) {
+    let shared = Arc::new(Mutex::new(OneshotState {
+        value: None,
+        sender_alive: true,
+        receiver_alive: true,
+        receiver_waker: None,
+    }));
+
+    (
+        Sender {
+            shared: shared.clone(),
+        },
+        Receiver { shared },
+    )
+}
+
+pub struct Sender<T> {
+    shared: Shared<T>,
+}
+
+impl<T> Sender<T> {
+    /// Hands back the value if the receiver has already been dropped.
+    pub fn send(self, value: T) -> Result<(), T> {
+        let waker = with_state(&self.shared, |state| {
+            if !state.receiver_alive {
+                return Err(value);
+            }
+
+            state.value = Some(value);
+            Ok(state.receiver_waker.take())
+        })?;
+
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+
+        Ok(())
+    }
+
+    pub fn is_closed(&self) -> bool {
+        with_state(&self.shared, |state| !state.receiver_alive)
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        let waker = with_state(&self.shared, |state| {
+            state.sender_alive = false;
+            state.receiver_waker.take()
+        });
+
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+}
+
+/// Resolves to the sent value, or `RecvError` if the sender was dropped.
+pub struct Receiver<T> {
+    shared: Shared<T>,
+}
+
+impl<T> Receiver<T> {
+    pub fn try_recv(&mut self) -> Option<T> {
+        with_state(&self.shared, |state| state.value.take())
+    }
+}
+
+impl<T> Future for Receiver<T> {
+    type Output = Result<T, RecvError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let result = with_state(&self.shared, |state| {
+            if let Some(value) = state.value.take() {
+                Some(Ok(value))
+            } else if !state.sender_alive {
+                Some(Err(RecvError))
+            } else {
+                state.receiver_waker = Some(cx.waker().clone());
+                None
+            }
+        });
+
+        match result {
+            Some(result) => Poll::Ready(result),
+            None => {
+                task::set_wait_reason(WaitReason::Other("oneshot"));
+                Poll::Pending
+            }
+        }
+    }
+}
+
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        let value = with_state(&self.shared, |state| {
+            state.receiver_alive = false;
+            state.value.take()
+        });
+        drop(value);
+    }
+}
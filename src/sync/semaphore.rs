@@ -0,0 +1,243 @@
+use alloc::{sync::Arc, vec::Vec};
+use core::{
+    fmt::Display,
+    future::Future,
+    pin::Pin,
+    sync::atomic::{AtomicBool, Ordering},
+    task::{Context, Poll},
+};
+
+use futures_util::task::AtomicWaker;
+use spin::Mutex;
+use x86_64::instructions::interrupts;
+
+use crate::task::{self, WaitReason};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct AcquireError;
+
+impl Display for AcquireError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "semaphore closed")
+    }
+}
+
+struct Waiter {
+    permits: usize,
+    assigned: AtomicBool,
+    waker: AtomicWaker,
+}
+
+struct SemaphoreState {
+    permits: usize,
+    closed: bool,
+    waiters: Vec<Arc<Waiter>>,
+}
+
+impl SemaphoreState {
+    /// Hands permits to waiters in the order they arrived. A waiter at the
+    /// front that wants more than is available holds up everyone behind it,
+    /// so large requests (like a writer taking every permit) aren't starved.
+    fn assign_permits(&mut self) {
+        while let Some(waiter) = self.waiters.first() {
+            if waiter.permits > self.permits && !self.closed {
+                break;
+            }
+
+            let waiter = self.waiters.remove(0);
+            if !self.closed {
+                self.permits -= waiter.permits;
+                waiter.assigned.store(true, Ordering::Release);
+            }
+            waiter.waker.wake();
+        }
+    }
+}
+
+/// A fair, async counting semaphore; the building block for the other
+/// primitives in `sync`.
+pub struct Semaphore {
+    state: Mutex<SemaphoreState>,
+}
+
+impl Semaphore {
+    pub const fn new(permits: usize) -> Self {
+        Self {
+            state: Mutex::new(SemaphoreState {
+                permits,
+                closed: false,
+                waiters: Vec::new(),
+            }),
+        }
+    }
+
+    fn with_state<R>(&self, f: impl FnOnce(&mut SemaphoreState) -> R) -> R {
+        interrupts::without_interrupts(|| f(&mut self.state.lock()))
+    }
+
+    pub fn available_permits(&self) -> usize {
+        self.with_state(|state| state.permits)
+    }
+
+    pub fn acquire(&self) -> Acquire<'_> {
+        self.acquire_many(1)
+    }
+
+    pub fn acquire_many(&self, permits: usize) -> Acquire<'_> {
+        Acquire {
+            semaphore: self,
+            permits,
+            waiter: None,
+        }
+    }
+
+    pub fn try_acquire(&self) -> Option<SemaphorePermit<'_>> {
+        self.try_acquire_many(1)
+    }
+
+    pub fn try_acquire_many(&self, permits: usize) -> Option<SemaphorePermit<'_>> {
+        let acquired = self.with_state(|state| {
+            if !state.closed && state.waiters.is_empty() && state.permits >= permits {
+                state.permits -= permits;
+                true
+            } else {
+                false
+            }
+        });
+
+        if acquired {
+            Some(SemaphorePermit {
+                semaphore: self,
+                permits,
+            })
+        } else {
+            None
+        }
+    }
+
+    pub fn add_permits(&self, permits: usize) {
+        self.with_state(|state| {
+            state.permits += permits;
+            state.assign_permits();
+        });
+    }
+
+    /// Fails every pending and future acquire.
+    pub fn close(&self) {
+        self.with_state(|state| {
+            state.closed = true;
+            state.assign_permits();
+        });
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.with_state(|state| state.closed)
+    }
+}
+
+pub struct Acquire<'a> {
+    semaphore: &'a Semaphore,
+    permits: usize,
+    waiter: Option<Arc<Waiter>>,
+}
+
+impl<'a> Future for Acquire<'a> {
+    type Output = Result<SemaphorePermit<'a>, AcquireError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let semaphore = self.semaphore;
+        let permits = self.permits;
+
+        let waiter = match &self.waiter {
+            Some(waiter) => {
+                waiter.waker.register(cx.waker());
+
+                if waiter.assigned.load(Ordering::Acquire) {
+                    self.waiter = None;
+                    return Poll::Ready(Ok(SemaphorePermit { semaphore, permits }));
+                }
+                if semaphore.is_closed() {
+                    self.waiter = None;
+                    return Poll::Ready(Err(AcquireError));
+                }
+
+                None
+            }
+            None => {
+                let waiter = semaphore.with_state(|state| {
+                    if state.closed {
+                        return Err(AcquireError);
+                    }
+
+                    if state.waiters.is_empty() && state.permits >= permits {
+                        state.permits -= permits;
+                        return Ok(None);
+                    }
+
+                    let waiter = Arc::new(Waiter {
+                        permits,
+                        assigned: AtomicBool::new(false),
+                        waker: AtomicWaker::new(),
+                    });
+                    waiter.waker.register(cx.waker());
+                    state.waiters.push(waiter.clone());
+
+                    Ok(Some(waiter))
+                });
+
+                match waiter {
+                    Err(error) => return Poll::Ready(Err(error)),
+                    Ok(None) => return Poll::Ready(Ok(SemaphorePermit { semaphore, permits })),
+                    Ok(Some(waiter)) => Some(waiter),
+                }
+            }
+        };
+
+        if waiter.is_some() {
+            self.waiter = waiter;
+        }
+
+        task::set_wait_reason(WaitReason::Other("semaphore"));
+        Poll::Pending
+    }
+}
+
+impl Drop for Acquire<'_> {
+    fn drop(&mut self) {
+        let waiter = match self.waiter.take() {
+            Some(waiter) => waiter,
+            None => return,
+        };
+
+        self.semaphore.with_state(|state| {
+            if waiter.assigned.load(Ordering::Acquire) {
+                // Permits were handed over but never collected.
+                state.permits += waiter.permits;
+            } else {
+                state.waiters.retain(|other| !Arc::ptr_eq(other, &waiter));
+            }
+
+            state.assign_permits();
+        });
+    }
+}
+
+/// Permits held from a `Semaphore`, returned when dropped.
+pub struct SemaphorePermit<'a> {
+    semaphore: &'a Semaphore,
+    permits: usize,
+}
+
+impl SemaphorePermit<'_> {
+    /// Keeps the permits out of the semaphore for good, e.g. while a value
+    /// sits in a bounded channel; `Semaphore::add_permits` gives them back.
+    pub fn forget(self) {
+        core::mem::forget(self)
+    }
+}
+
+impl Drop for SemaphorePermit<'_> {
+    fn drop(&mut self) {
+        self.semaphore.add_permits(self.permits);
+    }
+}
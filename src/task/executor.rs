@@ -1,63 +1,270 @@
-use core::task::{Context, Poll, Waker};
+use core::{
+    sync::atomic::{AtomicUsize, Ordering},
+    task::{Context, Poll},
+};
 
-use alloc::{collections::BTreeMap, sync::Arc};
-use crossbeam_queue::ArrayQueue;
+use alloc::{collections::BTreeMap, sync::Arc, vec::Vec};
+use crossbeam_queue::SegQueue;
+use spin::Mutex;
+use x86_64::instructions::interrupts;
 
-use super::{task::Task, task_id::TaskId, waker::TaskWaker};
+use super::{
+    latency,
+    priority::{Priority, PRIORITIES},
+    registry,
+    spawner::Spawner,
+    task::Task,
+    task_id::TaskId,
+    waker::TaskWaker,
+};
+use crate::{
+    thread,
+    time::{self, Instant},
+};
+
+/// How many times in a row a priority with queued tasks can be passed over
+/// for more urgent ones before it gets the next turn.
+const STARVATION_LIMIT: usize = 8;
+
+/// The state the executors on every CPU share. Each CPU has a run queue per
+/// priority and takes tasks from the others' when its own are empty; a task
+/// is taken out of `tasks` by whichever executor is polling it.
+pub(super) struct Shared {
+    tasks: Mutex<BTreeMap<TaskId, (Task, Arc<TaskWaker>)>>,
+    // Indexed by CPU, then by priority.
+    run_queues: Vec<Vec<Arc<SegQueue<TaskId>>>>,
+    new_tasks: SegQueue<Task>,
+    // Spawned and not yet completed, including tasks still in `new_tasks`.
+    live_tasks: AtomicUsize,
+}
+
+impl Shared {
+    fn with_tasks<R>(
+        &self,
+        f: impl FnOnce(&mut BTreeMap<TaskId, (Task, Arc<TaskWaker>)>) -> R,
+    ) -> R {
+        interrupts::without_interrupts(|| f(&mut self.tasks.lock()))
+    }
+
+    /// Hands `task` to whichever executor next looks for new tasks.
+    pub(super) fn submit(&self, task: Task) {
+        self.live_tasks.fetch_add(1, Ordering::AcqRel);
+        self.new_tasks.push(task);
+    }
+}
 
 pub struct Executor {
-    tasks: BTreeMap<TaskId, Task>,
-    task_queue: Arc<ArrayQueue<TaskId>>,
-    waker_cache: BTreeMap<TaskId, Waker>,
+    shared: Arc<Shared>,
+    cpu: usize,
+    passed_over: [usize; PRIORITIES],
 }
 
 impl Executor {
     pub fn new() -> Executor {
+        Executor::with_cpus(1)
+    }
+
+    /// Creates the executor for CPU 0 of `cpus`; `for_cpu` makes the rest.
+    pub fn with_cpus(cpus: usize) -> Executor {
+        assert!(cpus > 0, "an executor needs at least one CPU");
+
+        let shared = Shared {
+            tasks: Mutex::new(BTreeMap::new()),
+            run_queues: (0..cpus)
+                .map(|_| {
+                    Priority::ALL
+                        .iter()
+                        .map(|_| Arc::new(SegQueue::new()))
+                        .collect()
+                })
+                .collect(),
+            new_tasks: SegQueue::new(),
+            live_tasks: AtomicUsize::new(0),
+        };
+
         Executor {
-            tasks: BTreeMap::new(),
-            task_queue: Arc::new(ArrayQueue::new(100)),
-            waker_cache: BTreeMap::new(),
+            shared: Arc::new(shared),
+            cpu: 0,
+            passed_over: [0; PRIORITIES],
         }
     }
 
+    /// An executor for another CPU that shares this one's tasks.
+    pub fn for_cpu(&self, cpu: usize) -> Executor {
+        assert!(
+            cpu < self.shared.run_queues.len(),
+            "no run queue for CPU {}",
+            cpu
+        );
+
+        Executor {
+            shared: self.shared.clone(),
+            cpu,
+            passed_over: [0; PRIORITIES],
+        }
+    }
+
+    pub fn cpu(&self) -> usize {
+        self.cpu
+    }
+
+    pub fn spawner(&self) -> Spawner {
+        Spawner::new(self.shared.clone())
+    }
+
     pub fn spawn(&mut self, task: Task) {
+        self.shared.live_tasks.fetch_add(1, Ordering::AcqRel);
+        self.start(task);
+    }
+
+    fn start(&self, task: Task) {
         let task_id = task.id;
+        let run_queue = self.shared.run_queues[self.cpu][task.priority.index()].clone();
+        let waker = TaskWaker::new(task_id, run_queue);
+        registry::register(task_id, task.name.clone(), task.priority, waker.clone());
 
-        if self.tasks.insert(task.id, task).is_some() {
+        let previous = self
+            .shared
+            .with_tasks(|tasks| tasks.insert(task_id, (task, waker.clone())));
+        if previous.is_some() {
             panic!("can't spawn a task that's already running");
         }
 
-        self.task_queue.push(task_id).expect("task queue full");
+        waker.wake_task();
+    }
+
+    /// The number of tasks that have been spawned and haven't completed.
+    pub fn task_count(&self) -> usize {
+        self.shared.live_tasks.load(Ordering::Acquire)
+    }
+
+    /// The number of tasks waiting to be polled, on every CPU.
+    pub fn queued_tasks(&self) -> usize {
+        self.shared
+            .run_queues
+            .iter()
+            .flatten()
+            .map(|queue| queue.len())
+            .sum()
+    }
+
+    /// Returns whether there were any.
+    fn spawn_new_tasks(&self) -> bool {
+        let mut spawned = false;
+        while let Ok(task) = self.shared.new_tasks.pop() {
+            self.start(task);
+            spawned = true;
+        }
+
+        spawned
+    }
+
+    /// Takes the most urgent queued task, unless a less urgent priority has
+    /// been passed over `STARVATION_LIMIT` times, in which case it's that
+    /// priority's turn.
+    fn next_task(&mut self) -> Option<TaskId> {
+        let passed_over = self.passed_over;
+        let starved = Priority::ALL
+            .iter()
+            .filter(move |priority| passed_over[priority.index()] >= STARVATION_LIMIT);
+
+        let (priority, task_id) = starved
+            .chain(Priority::ALL.iter())
+            .find_map(|&priority| self.pop(priority).map(|task_id| (priority, task_id)))?;
+
+        for &other in Priority::ALL.iter() {
+            if other == priority {
+                self.passed_over[other.index()] = 0;
+            } else if self.has_queued(other) {
+                self.passed_over[other.index()] += 1;
+            }
+        }
+
+        Some(task_id)
+    }
+
+    /// Takes a task of `priority` from this CPU's run queue, or failing that
+    /// steals one from the next CPU along that has any.
+    fn pop(&self, priority: Priority) -> Option<TaskId> {
+        let queues = &self.shared.run_queues;
+
+        (0..queues.len())
+            .map(|offset| &queues[(self.cpu + offset) % queues.len()][priority.index()])
+            .find_map(|queue| queue.pop().ok())
+    }
+
+    fn has_queued(&self, priority: Priority) -> bool {
+        self.shared
+            .run_queues
+            .iter()
+            .any(|queues| !queues[priority.index()].is_empty())
+    }
+
+    fn has_work(&self) -> bool {
+        !self.shared.new_tasks.is_empty()
+            || Priority::ALL
+                .iter()
+                .any(|&priority| self.has_queued(priority))
     }
 
     fn run_ready_tasks(&mut self) {
-        let Self {
-            tasks,
-            task_queue,
-            waker_cache,
-        } = self;
-
-        while let Ok(task_id) = task_queue.pop() {
-            let task = match tasks.get_mut(&task_id) {
-                Some(task) => task,
-                None => continue,
-            };
-
-            let waker = waker_cache
-                .entry(task_id)
-                .or_insert_with(|| TaskWaker::new(task_id, task_queue.clone()));
-
-            let mut context = Context::from_waker(waker);
-            match task.poll(&mut context) {
-                Poll::Ready(()) => {
-                    tasks.remove(&task_id);
-                    waker_cache.remove(&task_id);
-                }
-                Poll::Pending => {}
+        self.spawn_new_tasks();
+
+        let mut now = time::now();
+        while let Some(task_id) = self.next_task() {
+            now = self.run_task(task_id, now);
+
+            // Tasks spawned by the task that just ran.
+            if self.spawn_new_tasks() {
+                now = time::now();
             }
         }
     }
 
+    /// Polls a task, given the time it's taken off the queue, and returns the
+    /// time the poll ended. Back to back polls share the reading in between,
+    /// so each poll costs a single read of the clock.
+    fn run_task(&self, task_id: TaskId, start: Instant) -> Instant {
+        let (mut task, waker) = match self.shared.with_tasks(|tasks| tasks.remove(&task_id)) {
+            Some(entry) => entry,
+            None => return start,
+        };
+
+        latency::record(task.priority, start - waker.queued_at());
+        waker.start_running();
+
+        if !registry::start_poll(&waker) {
+            println!("Aborting {} \"{}\"", task_id, task.name);
+            self.finish(task_id);
+            return start;
+        }
+
+        let (poll, end) = {
+            let waker = waker.waker();
+            let mut context = Context::from_waker(&waker);
+            let poll = task.poll(&mut context);
+            (poll, time::now())
+        };
+        registry::end_poll(&waker, end - start);
+
+        match poll {
+            Poll::Ready(()) => self.finish(task_id),
+            Poll::Pending => {
+                // Back in the table before it can be queued again.
+                self.shared
+                    .with_tasks(|tasks| tasks.insert(task_id, (task, waker.clone())));
+                waker.finish_running();
+            }
+        }
+
+        end
+    }
+
+    fn finish(&self, task_id: TaskId) {
+        registry::unregister(task_id);
+        self.shared.live_tasks.fetch_sub(1, Ordering::AcqRel);
+    }
+
     pub fn run(&mut self) -> ! {
         loop {
             self.run_ready_tasks();
@@ -65,13 +272,31 @@ impl Executor {
         }
     }
 
-    fn sleep_if_idle(&self) {
-        use x86_64::instructions::interrupts;
+    /// Runs until every spawned task has completed, wherever it ran.
+    pub fn run_to_completion(&mut self) {
+        loop {
+            self.run_ready_tasks();
 
+            if self.task_count() == 0 {
+                return;
+            }
+
+            self.sleep_if_idle();
+        }
+    }
+
+    fn sleep_if_idle(&self) {
         interrupts::disable();
 
-        if self.task_queue.is_empty() {
-            interrupts::enable_interrupts_and_hlt();
+        if !self.has_work() {
+            // With nothing to poll, let other kernel threads have the CPU
+            // rather than halting until the next interrupt.
+            if thread::has_ready_threads() {
+                interrupts::enable();
+                thread::yield_now();
+            } else {
+                interrupts::enable_interrupts_and_hlt();
+            }
         } else {
             interrupts::enable();
         }
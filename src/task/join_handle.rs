@@ -0,0 +1,167 @@
+use alloc::sync::Arc;
+use core::{
+    fmt::Display,
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use futures_util::{future::poll_fn, pin_mut, task::AtomicWaker};
+use spin::Mutex;
+
+use super::{registry, TaskId, WaitReason};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum JoinError {
+    /// The task was dropped before it completed.
+    Cancelled,
+}
+
+impl Display for JoinError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            JoinError::Cancelled => write!(f, "task was cancelled"),
+        }
+    }
+}
+
+enum JoinResult<T> {
+    Running,
+    Finished(T),
+    Cancelled,
+    Taken,
+}
+
+struct JoinState<T> {
+    result: Mutex<JoinResult<T>>,
+    waker: AtomicWaker,
+}
+
+impl<T> JoinState<T> {
+    fn finish(&self, result: JoinResult<T>) {
+        {
+            let mut current = self.result.lock();
+            if let JoinResult::Running = *current {
+                *current = result;
+            }
+        }
+
+        self.waker.wake();
+    }
+
+    fn is_cancelled(&self) -> bool {
+        matches!(*self.result.lock(), JoinResult::Cancelled)
+    }
+}
+
+/// Waits for a spawned task and yields its output. Dropping the handle
+/// cancels the task, unless it's been detached.
+#[must_use = "dropping a JoinHandle cancels its task"]
+pub struct JoinHandle<T> {
+    task_id: TaskId,
+    state: Arc<JoinState<T>>,
+    detached: bool,
+}
+
+impl<T> JoinHandle<T> {
+    pub fn task_id(&self) -> TaskId {
+        self.task_id
+    }
+
+    /// Stops the task; awaiting the handle then yields
+    /// `JoinError::Cancelled` unless the task had already finished. The
+    /// task's future isn't polled again, even if it hasn't started yet.
+    pub fn abort(&self) {
+        self.state.finish(JoinResult::Cancelled);
+        registry::abort(self.task_id);
+    }
+
+    /// Lets the task run to completion with nothing waiting for it.
+    pub fn detach(mut self) {
+        self.detached = true;
+    }
+}
+
+impl<T> Drop for JoinHandle<T> {
+    fn drop(&mut self) {
+        if !self.detached {
+            self.abort();
+        }
+    }
+}
+
+impl<T> Future for JoinHandle<T> {
+    type Output = Result<T, JoinError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.state.waker.register(cx.waker());
+
+        let mut result = self.state.result.lock();
+        match core::mem::replace(&mut *result, JoinResult::Taken) {
+            JoinResult::Running => {
+                *result = JoinResult::Running;
+                registry::set_wait_reason(WaitReason::Join(self.task_id));
+                Poll::Pending
+            }
+            JoinResult::Finished(output) => Poll::Ready(Ok(output)),
+            JoinResult::Cancelled => Poll::Ready(Err(JoinError::Cancelled)),
+            JoinResult::Taken => panic!("JoinHandle polled after completion"),
+        }
+    }
+}
+
+/// Marks the task cancelled if it's dropped before finishing.
+struct CancelOnDrop<T> {
+    state: Arc<JoinState<T>>,
+}
+
+impl<T> Drop for CancelOnDrop<T> {
+    fn drop(&mut self) {
+        self.state.finish(JoinResult::Cancelled);
+    }
+}
+
+/// Wraps `future` so its output is delivered to the returned handle, which
+/// refers to the task that will be given `task_id`.
+pub(super) fn joinable<F>(
+    task_id: TaskId,
+    future: F,
+) -> (impl Future<Output = ()>, JoinHandle<F::Output>)
+where
+    F: Future,
+{
+    let state = Arc::new(JoinState {
+        result: Mutex::new(JoinResult::Running),
+        waker: AtomicWaker::new(),
+    });
+
+    let guard = CancelOnDrop {
+        state: state.clone(),
+    };
+
+    let task = async move {
+        pin_mut!(future);
+
+        // Checked before every poll, as the task may be cancelled before
+        // the registry knows about it.
+        let output = poll_fn(|cx| {
+            if guard.state.is_cancelled() {
+                return Poll::Ready(None);
+            }
+            future.as_mut().poll(cx).map(Some)
+        })
+        .await;
+
+        if let Some(output) = output {
+            guard.state.finish(JoinResult::Finished(output));
+        }
+    };
+
+    let join_handle = JoinHandle {
+        task_id,
+        state,
+        detached: false,
+    };
+
+    (task, join_handle)
+}
@@ -0,0 +1,84 @@
+use core::{
+    fmt::Display,
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+
+use super::priority::{Priority, PRIORITIES};
+
+struct LatencyCounters {
+    polls: AtomicU64,
+    total_nanoseconds: AtomicU64,
+    max_nanoseconds: AtomicU64,
+}
+
+static COUNTERS: [LatencyCounters; PRIORITIES] = [LatencyCounters {
+    polls: AtomicU64::new(0),
+    total_nanoseconds: AtomicU64::new(0),
+    max_nanoseconds: AtomicU64::new(0),
+}; PRIORITIES];
+
+/// How long tasks of one priority sat in a run queue between being woken and
+/// being polled.
+#[derive(Debug, Copy, Clone)]
+pub struct QueueLatency {
+    pub priority: Priority,
+    pub polls: u64,
+    pub total: Duration,
+    pub max: Duration,
+}
+
+impl QueueLatency {
+    pub fn average(&self) -> Duration {
+        match self.polls {
+            0 => Duration::from_secs(0),
+            polls => self.total / polls as u32,
+        }
+    }
+}
+
+impl Display for QueueLatency {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "{}: {} polls, {} us average, {} us max queue latency",
+            self.priority,
+            self.polls,
+            self.average().as_micros(),
+            self.max.as_micros()
+        )
+    }
+}
+
+pub(super) fn record(priority: Priority, latency: Duration) {
+    let counters = &COUNTERS[priority.index()];
+    let nanoseconds = latency.as_nanos() as u64;
+
+    counters.polls.fetch_add(1, Ordering::Relaxed);
+    counters
+        .total_nanoseconds
+        .fetch_add(nanoseconds, Ordering::Relaxed);
+    counters
+        .max_nanoseconds
+        .fetch_max(nanoseconds, Ordering::Relaxed);
+}
+
+/// Queue latency so far for each priority, most urgent first.
+pub fn queue_latency() -> [QueueLatency; PRIORITIES] {
+    let latency = |priority: Priority| {
+        let counters = &COUNTERS[priority.index()];
+
+        QueueLatency {
+            priority,
+            polls: counters.polls.load(Ordering::Relaxed),
+            total: Duration::from_nanos(counters.total_nanoseconds.load(Ordering::Relaxed)),
+            max: Duration::from_nanos(counters.max_nanoseconds.load(Ordering::Relaxed)),
+        }
+    };
+
+    [
+        latency(Priority::BottomHalf),
+        latency(Priority::Interactive),
+        latency(Priority::Background),
+    ]
+}
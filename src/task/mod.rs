@@ -1,14 +1,50 @@
 mod executor;
+mod join_handle;
+mod latency;
+mod priority;
+mod registry;
+mod spawner;
 mod task;
 mod task_id;
+mod wait_reason;
 mod waker;
+mod yield_now;
 
 pub use executor::Executor;
+pub use join_handle::{JoinError, JoinHandle};
+pub use latency::{queue_latency, QueueLatency};
+pub use priority::Priority;
+pub use registry::{abort, current_task, dump, set_wait_reason, tasks, TaskInfo, TaskState};
+pub use spawner::Spawner;
 pub use task::Task;
 pub use task_id::TaskId;
+pub use wait_reason::{waiting_on, WaitReason, WaitingOn};
+pub use yield_now::{yield_now, YieldNow};
 
+use spin::Once;
+
+use crate::smp;
+
+static BOOT_EXECUTOR: Once<Executor> = Once::new();
+
+/// Creates the boot processor's executor, with a run queue for every CPU
+/// `smp::init` started, and lets the others start theirs.
 pub fn init() -> Executor {
-    let executor = Executor::new();
+    let executor = Executor::with_cpus(smp::cpu_count());
+    BOOT_EXECUTOR.call_once(|| executor.for_cpu(0));
 
     executor
 }
+
+/// Runs an application processor's executor, once the boot processor has
+/// called `init`.
+pub fn run_ap(cpu: usize) -> ! {
+    let mut executor = loop {
+        match BOOT_EXECUTOR.r#try() {
+            Some(executor) => break executor.for_cpu(cpu),
+            None => x86_64::instructions::hlt(),
+        }
+    };
+
+    executor.run()
+}
@@ -0,0 +1,40 @@
+use core::fmt::Display;
+
+/// How urgently a task wants the CPU. Executors always run the most urgent
+/// queued task first, except that a class passed over too often gets a turn
+/// so it can't be starved.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Priority {
+    /// Finishes the work of an interrupt handler, e.g. draining a device's
+    /// buffers before they overflow.
+    BottomHalf,
+    /// Something a person is waiting on, like keyboard input.
+    Interactive,
+    /// Bulk work that only needs to make progress eventually.
+    Background,
+}
+
+pub const PRIORITIES: usize = 3;
+
+impl Priority {
+    /// Every priority, most urgent first.
+    pub const ALL: [Priority; PRIORITIES] = [
+        Priority::BottomHalf,
+        Priority::Interactive,
+        Priority::Background,
+    ];
+
+    pub fn index(self) -> usize {
+        self as usize
+    }
+}
+
+impl Display for Priority {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Priority::BottomHalf => write!(f, "bottom half"),
+            Priority::Interactive => write!(f, "interactive"),
+            Priority::Background => write!(f, "background"),
+        }
+    }
+}
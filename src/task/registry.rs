@@ -0,0 +1,176 @@
+use alloc::{collections::BTreeMap, string::String, sync::Arc, vec::Vec};
+use core::{
+    fmt::Display,
+    ptr,
+    sync::atomic::{AtomicPtr, Ordering},
+    time::Duration,
+};
+
+use lazy_static::lazy_static;
+use spin::Mutex;
+use x86_64::instructions::interrupts;
+
+use super::{latency::queue_latency, waker::TaskWaker, Priority, TaskId, WaitReason};
+use crate::smp::{self, MAX_CPUS};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TaskState {
+    Queued,
+    Running,
+    Waiting,
+}
+
+/// A snapshot of a live task.
+#[derive(Debug, Clone)]
+pub struct TaskInfo {
+    pub id: TaskId,
+    pub name: String,
+    pub priority: Priority,
+    pub state: TaskState,
+    pub polls: u64,
+    pub poll_time: Duration,
+    pub wait_reason: Option<WaitReason>,
+}
+
+impl Display for TaskInfo {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "{} \"{}\" ({}): {:?}, {} polls, {} us polling",
+            self.id,
+            self.name,
+            self.priority,
+            self.state,
+            self.polls,
+            self.poll_time.as_micros()
+        )?;
+
+        if let Some(wait_reason) = self.wait_reason {
+            write!(f, ", waiting on {}", wait_reason)?;
+        }
+
+        Ok(())
+    }
+}
+
+// The statistics live in the waker, so polling doesn't need the lock.
+struct Entry {
+    name: String,
+    priority: Priority,
+    waker: Arc<TaskWaker>,
+}
+
+lazy_static! {
+    static ref TASKS: Mutex<BTreeMap<TaskId, Entry>> = Mutex::new(BTreeMap::new());
+}
+
+// The waker of the task each CPU is polling, kept alive by the executor for
+// as long as the poll lasts, or null.
+static CURRENT_TASK: [AtomicPtr<TaskWaker>; MAX_CPUS] = [AtomicPtr::new(ptr::null_mut()); MAX_CPUS];
+
+fn with_tasks<R>(f: impl FnOnce(&mut BTreeMap<TaskId, Entry>) -> R) -> R {
+    interrupts::without_interrupts(|| f(&mut TASKS.lock()))
+}
+
+pub(super) fn register(id: TaskId, name: String, priority: Priority, waker: Arc<TaskWaker>) {
+    let entry = Entry {
+        name,
+        priority,
+        waker,
+    };
+
+    with_tasks(|tasks| tasks.insert(id, entry));
+}
+
+pub(super) fn unregister(id: TaskId) {
+    with_tasks(|tasks| tasks.remove(&id));
+}
+
+/// Marks the task as the one this CPU is polling, or returns false if it
+/// has been aborted and should be dropped instead of polled.
+pub(super) fn start_poll(waker: &Arc<TaskWaker>) -> bool {
+    if waker.is_aborted() {
+        return false;
+    }
+
+    waker.set_wait_reason(None);
+    CURRENT_TASK[smp::current_cpu()].store(Arc::as_ptr(waker) as *mut _, Ordering::Relaxed);
+    true
+}
+
+pub(super) fn end_poll(waker: &TaskWaker, elapsed: Duration) {
+    CURRENT_TASK[smp::current_cpu()].store(ptr::null_mut(), Ordering::Relaxed);
+    waker.record_poll(elapsed);
+}
+
+fn with_current<R>(f: impl FnOnce(&TaskWaker) -> R) -> Option<R> {
+    let current = CURRENT_TASK[smp::current_cpu()].load(Ordering::Relaxed);
+
+    // Only this CPU sets its slot, and the waker outlives the poll.
+    unsafe { current.as_ref() }.map(f)
+}
+
+pub fn current_task() -> Option<TaskId> {
+    with_current(|waker| waker.task_id())
+}
+
+/// Records what the currently running task is about to wait for. Leaf
+/// futures call this when they return `Poll::Pending`; see `waiting_on`.
+pub fn set_wait_reason(reason: WaitReason) {
+    with_current(|waker| waker.set_wait_reason(Some(reason)));
+}
+
+/// Stops a task: it's dropped instead of being polled again, and anything
+/// joining it sees it as cancelled. Returns false if there's no such task.
+pub fn abort(id: TaskId) -> bool {
+    match with_tasks(|tasks| tasks.get(&id).map(|entry| entry.waker.clone())) {
+        Some(waker) => {
+            waker.abort();
+            true
+        }
+        None => false,
+    }
+}
+
+pub fn tasks() -> Vec<TaskInfo> {
+    with_tasks(|tasks| {
+        tasks
+            .iter()
+            .map(|(&id, entry)| {
+                let waker = &entry.waker;
+                let state = if waker.is_running() {
+                    TaskState::Running
+                } else if waker.is_queued() {
+                    TaskState::Queued
+                } else {
+                    TaskState::Waiting
+                };
+
+                TaskInfo {
+                    id,
+                    name: entry.name.clone(),
+                    priority: entry.priority,
+                    state,
+                    polls: waker.polls(),
+                    poll_time: waker.poll_time(),
+                    wait_reason: waker.wait_reason(),
+                }
+            })
+            .collect()
+    })
+}
+
+/// Prints every live task, to find the one that's stuck, and how long each
+/// priority has been waiting to run.
+pub fn dump() {
+    let tasks = tasks();
+
+    println!("{} tasks:", tasks.len());
+    for task in tasks {
+        println!("  - {}", task);
+    }
+
+    for latency in queue_latency().iter() {
+        println!("  {}", latency);
+    }
+}
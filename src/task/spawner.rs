@@ -0,0 +1,38 @@
+use alloc::{string::String, sync::Arc};
+use core::future::Future;
+
+use super::{executor::Shared, join_handle::joinable, JoinHandle, Priority, Task, TaskId};
+
+/// A cloneable handle for spawning tasks onto the executors from anywhere,
+/// including from inside other tasks. Spawned tasks are picked up by the
+/// next executor, on any CPU, that looks for work.
+#[derive(Clone)]
+pub struct Spawner {
+    shared: Arc<Shared>,
+}
+
+impl Spawner {
+    pub(super) fn new(shared: Arc<Shared>) -> Self {
+        Self { shared }
+    }
+
+    pub fn spawn<F>(
+        &self,
+        name: impl Into<String>,
+        priority: Priority,
+        future: F,
+    ) -> JoinHandle<F::Output>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        let task_id = TaskId::new();
+        let (future, join_handle) = joinable(task_id, future);
+        self.spawn_task(Task::with_id(task_id, name, priority, future));
+        join_handle
+    }
+
+    pub fn spawn_task(&self, task: Task) {
+        self.shared.submit(task);
+    }
+}
@@ -1,21 +1,38 @@
-use alloc::boxed::Box;
+use alloc::{boxed::Box, string::String};
 use core::{
     future::Future,
     pin::Pin,
     task::{Context, Poll},
 };
 
-use super::task_id::TaskId;
+use super::{priority::Priority, task_id::TaskId};
 
 pub struct Task {
     pub id: TaskId,
-    future: Pin<Box<dyn Future<Output = ()>>>,
+    pub name: String,
+    pub priority: Priority,
+    future: Pin<Box<dyn Future<Output = ()> + Send>>,
 }
 
 impl Task {
-    pub fn new(future: impl Future<Output = ()> + 'static) -> Task {
+    pub fn new(
+        name: impl Into<String>,
+        priority: Priority,
+        future: impl Future<Output = ()> + Send + 'static,
+    ) -> Task {
+        Self::with_id(TaskId::new(), name, priority, future)
+    }
+
+    pub(super) fn with_id(
+        id: TaskId,
+        name: impl Into<String>,
+        priority: Priority,
+        future: impl Future<Output = ()> + Send + 'static,
+    ) -> Task {
         Task {
-            id: TaskId::new(),
+            id,
+            name: name.into(),
+            priority,
             future: Box::pin(future),
         }
     }
@@ -1,6 +1,9 @@
-use core::sync::atomic::{AtomicU64, Ordering};
+use core::{
+    fmt::Display,
+    sync::atomic::{AtomicU64, Ordering},
+};
 
-#[derive(Copy, Clone, PartialOrd, PartialEq, Eq, Ord)]
+#[derive(Copy, Clone, PartialOrd, PartialEq, Eq, Ord, Hash, Debug)]
 pub struct TaskId(u64);
 
 impl TaskId {
@@ -8,4 +11,14 @@ impl TaskId {
         static NEXT_ID: AtomicU64 = AtomicU64::new(0);
         TaskId(NEXT_ID.fetch_add(1, Ordering::Relaxed))
     }
+
+    pub fn as_u64(&self) -> u64 {
+        self.0
+    }
+}
+
+impl Display for TaskId {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "Task #{}", self.0)
+    }
 }
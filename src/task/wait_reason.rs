@@ -0,0 +1,57 @@
+use core::{
+    fmt::Display,
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use super::{registry, TaskId};
+use crate::time::Instant;
+
+/// What a pending task is blocked on, as shown by `task::dump`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WaitReason {
+    Irq(u8),
+    Sleep(Instant),
+    Join(TaskId),
+    Other(&'static str),
+}
+
+impl Display for WaitReason {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            WaitReason::Irq(irq) => write!(f, "IRQ {}", irq),
+            WaitReason::Sleep(deadline) => write!(f, "sleep until {}", deadline),
+            WaitReason::Join(task_id) => write!(f, "join {}", task_id),
+            WaitReason::Other(reason) => write!(f, "{}", reason),
+        }
+    }
+}
+
+/// Records `reason` against the current task whenever `future` is pending.
+pub fn waiting_on<F: Future>(reason: WaitReason, future: F) -> WaitingOn<F> {
+    WaitingOn { reason, future }
+}
+
+pub struct WaitingOn<F> {
+    reason: WaitReason,
+    future: F,
+}
+
+impl<F: Future> Future for WaitingOn<F> {
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<F::Output> {
+        let reason = self.reason;
+
+        // `future` is never moved out of the pinned `WaitingOn`.
+        let future = unsafe { self.map_unchecked_mut(|waiting_on| &mut waiting_on.future) };
+
+        let poll = future.poll(cx);
+        if poll.is_pending() {
+            registry::set_wait_reason(reason);
+        }
+
+        poll
+    }
+}
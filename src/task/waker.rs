@@ -1,25 +1,155 @@
-use core::task::Waker;
+use core::{
+    sync::atomic::{AtomicBool, AtomicU64, AtomicU8, Ordering},
+    task::Waker,
+    time::Duration,
+};
 
 use alloc::{sync::Arc, task::Wake};
-use crossbeam_queue::ArrayQueue;
+use crossbeam_queue::SegQueue;
+use spin::Mutex;
 
-use super::task_id::TaskId;
+use super::{task_id::TaskId, WaitReason};
+use crate::time::{self, Instant};
 
+const IDLE: u8 = 0;
+const QUEUED: u8 = 1;
+const RUNNING: u8 = 2;
+const NOTIFIED: u8 = 3;
+
+/// Queues its task on a run queue when woken. A task is only ever queued
+/// once at a time, however many wakeups arrive before it runs, so the queues
+/// can't grow beyond the number of tasks. Nor is it queued while it's being
+/// polled; a wakeup then queues it once the poll is over, so no two CPUs
+/// ever poll the same task.
+///
+/// It also keeps the task's statistics, so polling one doesn't touch any
+/// state shared between tasks.
 pub struct TaskWaker {
     task_id: TaskId,
-    task_queue: Arc<ArrayQueue<TaskId>>,
+    state: AtomicU8,
+    task_queue: Arc<SegQueue<TaskId>>,
+    // When the task was last pushed onto `task_queue`, in nanoseconds.
+    queued_at: AtomicU64,
+    polls: AtomicU64,
+    poll_nanoseconds: AtomicU64,
+    aborted: AtomicBool,
+    wait_reason: Mutex<Option<WaitReason>>,
 }
 
 impl TaskWaker {
-    pub fn new(task_id: TaskId, task_queue: Arc<ArrayQueue<TaskId>>) -> Waker {
-        Waker::from(Arc::new(Self {
+    pub fn new(task_id: TaskId, task_queue: Arc<SegQueue<TaskId>>) -> Arc<Self> {
+        Arc::new(Self {
             task_id,
+            state: AtomicU8::new(IDLE),
             task_queue,
-        }))
+            queued_at: AtomicU64::new(0),
+            polls: AtomicU64::new(0),
+            poll_nanoseconds: AtomicU64::new(0),
+            aborted: AtomicBool::new(false),
+            wait_reason: Mutex::new(None),
+        })
+    }
+
+    pub fn task_id(&self) -> TaskId {
+        self.task_id
+    }
+
+    pub fn waker(self: &Arc<Self>) -> Waker {
+        Waker::from(self.clone())
+    }
+
+    pub fn wake_task(&self) {
+        let mut state = self.state.load(Ordering::Acquire);
+
+        loop {
+            let next = match state {
+                IDLE => QUEUED,
+                RUNNING => NOTIFIED,
+                _ => return,
+            };
+
+            match self
+                .state
+                .compare_exchange_weak(state, next, Ordering::AcqRel, Ordering::Acquire)
+            {
+                Ok(_) => break,
+                Err(actual) => state = actual,
+            }
+        }
+
+        if state == IDLE {
+            self.push();
+        }
+    }
+
+    fn push(&self) {
+        self.queued_at
+            .store(time::now().as_nanoseconds(), Ordering::Relaxed);
+        self.task_queue.push(self.task_id);
+    }
+
+    /// When the task was last queued to be polled.
+    pub fn queued_at(&self) -> Instant {
+        Instant::from_nanoseconds(self.queued_at.load(Ordering::Relaxed))
+    }
+
+    pub fn is_queued(&self) -> bool {
+        self.state.load(Ordering::Acquire) == QUEUED
+    }
+
+    pub fn is_running(&self) -> bool {
+        matches!(self.state.load(Ordering::Acquire), RUNNING | NOTIFIED)
     }
 
-    fn wake_task(&self) {
-        self.task_queue.push(self.task_id).expect("task queue full");
+    /// Marks the task to be dropped instead of polled, and queues it so that
+    /// happens promptly.
+    pub fn abort(&self) {
+        self.aborted.store(true, Ordering::Release);
+        self.wake_task();
+    }
+
+    pub fn is_aborted(&self) -> bool {
+        self.aborted.load(Ordering::Acquire)
+    }
+
+    pub fn record_poll(&self, elapsed: Duration) {
+        self.polls.fetch_add(1, Ordering::Relaxed);
+        self.poll_nanoseconds
+            .fetch_add(elapsed.as_nanos() as u64, Ordering::Relaxed);
+    }
+
+    pub fn polls(&self) -> u64 {
+        self.polls.load(Ordering::Relaxed)
+    }
+
+    pub fn poll_time(&self) -> Duration {
+        Duration::from_nanos(self.poll_nanoseconds.load(Ordering::Relaxed))
+    }
+
+    pub fn set_wait_reason(&self, reason: Option<WaitReason>) {
+        *self.wait_reason.lock() = reason;
+    }
+
+    pub fn wait_reason(&self) -> Option<WaitReason> {
+        *self.wait_reason.lock()
+    }
+
+    /// Called as the task is taken off a run queue to be polled.
+    pub fn start_running(&self) {
+        self.state.store(RUNNING, Ordering::Release);
+    }
+
+    /// Called after a poll that returned `Pending`; queues the task again
+    /// if it was woken while it ran.
+    pub fn finish_running(&self) {
+        let finished =
+            self.state
+                .compare_exchange(RUNNING, IDLE, Ordering::AcqRel, Ordering::Acquire);
+
+        if finished.is_err() {
+            self.state.store(QUEUED, Ordering::Release);
+            self.push();
+        }
     }
 }
 
@@ -0,0 +1,42 @@
+use alloc::sync::Arc;
+use core::{
+    future::Future,
+    task::{Context, Poll, Wake, Waker},
+};
+
+use futures_util::pin_mut;
+
+use super::ThreadId;
+
+struct ThreadWaker {
+    thread: ThreadId,
+}
+
+impl Wake for ThreadWaker {
+    fn wake(self: Arc<Self>) {
+        self.wake_by_ref()
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        super::unpark(self.thread)
+    }
+}
+
+/// Runs `future` to completion on the current thread, blocking it while the
+/// future is pending. Lets threads use the async primitives in `sync` and
+/// `time`.
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    let waker = Waker::from(Arc::new(ThreadWaker {
+        thread: super::current(),
+    }));
+    let mut cx = Context::from_waker(&waker);
+
+    pin_mut!(future);
+    loop {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+            return output;
+        }
+
+        super::park();
+    }
+}
@@ -0,0 +1,96 @@
+use x86_64::VirtAddr;
+
+// Only the callee-saved registers need saving: everything else has already
+// been spilled by the caller of `switch_context`, or by the interrupt handler
+// if the thread was preempted.
+global_asm!(
+    r#"
+.intel_syntax noprefix
+.global switch_context
+switch_context:
+    push rbp
+    push rbx
+    push r12
+    push r13
+    push r14
+    push r15
+    mov [rdi], rsp
+    mov rsp, rsi
+    pop r15
+    pop r14
+    pop r13
+    pop r12
+    pop rbx
+    pop rbp
+    ret
+
+.global thread_trampoline
+thread_trampoline:
+    and rsp, -16
+    call thread_entry
+    ud2
+.att_syntax
+"#
+);
+
+extern "C" {
+    fn switch_context(old_rsp: *mut u64, new_rsp: u64);
+    fn thread_trampoline();
+}
+
+// Callee-saved registers popped by `switch_context`, then its return
+// address, then a null return address to end backtraces.
+const INITIAL_FRAME_WORDS: usize = 8;
+
+/// Lays out a new thread's stack so that switching to it returns into
+/// `thread_trampoline`, which calls `thread_entry`. Returns the saved stack
+/// pointer.
+pub fn initial_stack(top: VirtAddr) -> u64 {
+    let top = top.align_down(16u64);
+    let frame = (top - INITIAL_FRAME_WORDS as u64 * 8).as_mut_ptr::<u64>();
+
+    unsafe {
+        for index in 0..INITIAL_FRAME_WORDS {
+            frame.add(index).write(0);
+        }
+        frame
+            .add(INITIAL_FRAME_WORDS - 2)
+            .write(thread_trampoline as usize as u64);
+    }
+
+    frame as u64
+}
+
+/// The x87/SSE register file, in the format used by `fxsave`.
+#[repr(C, align(16))]
+pub struct FpuState([u8; 512]);
+
+impl FpuState {
+    /// The state after `fninit`: all exceptions masked, round to nearest.
+    pub fn new() -> Self {
+        let mut state = [0; 512];
+        state[0..2].copy_from_slice(&0x037Fu16.to_le_bytes());
+        state[24..28].copy_from_slice(&0x1F80u32.to_le_bytes());
+        FpuState(state)
+    }
+
+    pub fn save(&mut self) {
+        unsafe { asm!("fxsave64 [{}]", in(reg) self.0.as_mut_ptr(), options(nostack)) }
+    }
+
+    pub fn restore(&self) {
+        unsafe { asm!("fxrstor64 [{}]", in(reg) self.0.as_ptr(), options(nostack)) }
+    }
+}
+
+/// Saves the running thread's registers into `old_rsp` and `old_fpu` and
+/// resumes the thread described by `new_rsp`. Returns once something
+/// switches back.
+///
+/// Safety: interrupts must be disabled, and both pointers must stay valid
+/// until the thread is resumed.
+pub unsafe fn switch(old_rsp: *mut u64, old_fpu: *mut FpuState, new_rsp: u64) {
+    (*old_fpu).save();
+    switch_context(old_rsp, new_rsp);
+    (*old_fpu).restore();
+}
@@ -0,0 +1,28 @@
+use super::{block_on, ThreadId};
+use crate::sync::oneshot;
+
+/// Owns the result of a thread started with `thread::spawn`.
+pub struct ThreadHandle<T> {
+    id: ThreadId,
+    result: oneshot::Receiver<T>,
+}
+
+impl<T> ThreadHandle<T> {
+    pub(super) fn new(id: ThreadId, result: oneshot::Receiver<T>) -> Self {
+        Self { id, result }
+    }
+
+    pub fn id(&self) -> ThreadId {
+        self.id
+    }
+
+    /// Blocks the calling thread until the thread finishes.
+    pub fn join(self) -> T {
+        block_on(self.result).expect("thread exited without a result")
+    }
+
+    /// Waits for the thread to finish from an async task.
+    pub async fn wait(self) -> T {
+        self.result.await.expect("thread exited without a result")
+    }
+}
@@ -0,0 +1,109 @@
+mod block_on;
+mod context;
+mod handle;
+mod scheduler;
+mod thread_id;
+
+pub use block_on::block_on;
+pub use handle::ThreadHandle;
+pub use scheduler::{ThreadInfo, ThreadState, TIME_SLICE_TICKS};
+pub use thread_id::ThreadId;
+
+use alloc::{boxed::Box, string::String, vec::Vec};
+use core::time::Duration;
+
+use x86_64::instructions::interrupts;
+
+use crate::{sync::oneshot, time};
+
+/// Turns the boot context into the "main" thread and starts preemptive
+/// scheduling on the boot processor. Needs the timer running.
+pub fn init() {
+    scheduler::init(idle);
+    println!(
+        "Threads: preemptive round robin, {} tick time slices",
+        TIME_SLICE_TICKS
+    );
+}
+
+fn idle() {
+    loop {
+        scheduler::reap();
+
+        interrupts::disable();
+        if scheduler::has_ready_threads() {
+            interrupts::enable();
+            yield_now();
+        } else {
+            interrupts::enable_interrupts_and_hlt();
+        }
+    }
+}
+
+/// Starts `f` on a new kernel thread with its own stack. It runs
+/// concurrently with everything else, and is preempted when its time slice
+/// runs out.
+pub fn spawn<F, T>(name: impl Into<String>, f: F) -> ThreadHandle<T>
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+{
+    scheduler::reap();
+
+    let (sender, receiver) = oneshot::channel();
+    let id = scheduler::add_thread(
+        name.into(),
+        Box::new(move || {
+            let _ = sender.send(f());
+        }),
+    );
+
+    ThreadHandle::new(id, receiver)
+}
+
+pub fn current() -> ThreadId {
+    scheduler::current().expect("not running on a kernel thread")
+}
+
+/// Gives the rest of the time slice to the next ready thread, if any.
+pub fn yield_now() {
+    interrupts::without_interrupts(|| scheduler::reschedule(ThreadState::Ready));
+}
+
+/// Blocks the current thread until `unpark` is called for it. Returns
+/// immediately if it already was since the last `park`.
+pub fn park() {
+    interrupts::without_interrupts(|| scheduler::reschedule(ThreadState::Blocked));
+}
+
+pub fn unpark(id: ThreadId) {
+    scheduler::unpark(id)
+}
+
+/// Blocks the current thread for at least `duration`.
+pub fn sleep(duration: Duration) {
+    block_on(time::sleep(duration))
+}
+
+/// Ends the current thread.
+pub fn exit() -> ! {
+    interrupts::disable();
+    scheduler::reschedule(ThreadState::Finished);
+    unreachable!("finished thread was resumed")
+}
+
+pub fn threads() -> Vec<ThreadInfo> {
+    scheduler::threads()
+}
+
+pub(crate) fn timer_tick() {
+    scheduler::timer_tick()
+}
+
+pub(crate) fn preempt_if_needed() {
+    scheduler::preempt_if_needed()
+}
+
+pub(crate) fn has_ready_threads() -> bool {
+    scheduler::has_ready_threads()
+}
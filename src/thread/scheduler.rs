@@ -0,0 +1,329 @@
+use alloc::{
+    boxed::Box,
+    collections::{BTreeMap, VecDeque},
+    string::String,
+    vec::Vec,
+};
+use core::{
+    fmt::Display,
+    sync::atomic::{AtomicBool, AtomicU32, Ordering},
+};
+
+use spin::{Mutex, Once};
+use x86_64::instructions::interrupts;
+
+use super::{
+    context::{self, FpuState},
+    ThreadId,
+};
+use crate::{memory::stack::KernelStack, smp};
+
+const STACK_PAGES: u64 = 16;
+
+/// How many timer ticks a thread runs for before it's preempted.
+pub const TIME_SLICE_TICKS: u32 = 10;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ThreadState {
+    Ready,
+    Running,
+    Blocked,
+    Finished,
+}
+
+#[derive(Debug, Clone)]
+pub struct ThreadInfo {
+    pub id: ThreadId,
+    pub name: String,
+    pub state: ThreadState,
+}
+
+impl Display for ThreadInfo {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{} \"{}\": {:?}", self.id, self.name, self.state)
+    }
+}
+
+struct Thread {
+    id: ThreadId,
+    name: String,
+    state: ThreadState,
+    // `None` for the boot thread, which keeps the bootloader's stack.
+    stack: Option<KernelStack>,
+    rsp: u64,
+    fpu: FpuState,
+    unparked: bool,
+    entry: Option<Box<dyn FnOnce() + Send>>,
+}
+
+impl Thread {
+    fn new(name: String, entry: Box<dyn FnOnce() + Send>) -> Box<Self> {
+        let stack = KernelStack::allocate(STACK_PAGES);
+        let rsp = context::initial_stack(stack.top());
+
+        Box::new(Self {
+            id: ThreadId::new(),
+            name,
+            state: ThreadState::Ready,
+            stack: Some(stack),
+            rsp,
+            fpu: FpuState::new(),
+            unparked: false,
+            entry: Some(entry),
+        })
+    }
+
+    fn info(&self) -> ThreadInfo {
+        ThreadInfo {
+            id: self.id,
+            name: self.name.clone(),
+            state: self.state,
+        }
+    }
+}
+
+struct Scheduler {
+    threads: BTreeMap<ThreadId, Box<Thread>>,
+    ready: VecDeque<ThreadId>,
+    current: ThreadId,
+    idle: ThreadId,
+    // Threads that have exited but are still on their own stack; freed by
+    // `reap` once something else is running.
+    finished: Vec<Box<Thread>>,
+}
+
+impl Scheduler {
+    fn current_thread(&mut self) -> &mut Thread {
+        let current = self.current;
+        self.threads.get_mut(&current).unwrap()
+    }
+
+    /// Moves the current thread to `state` and picks the next one to run.
+    /// Returns the threads to switch between, or `None` to carry on with the
+    /// current one.
+    fn switch_from_current(&mut self, state: ThreadState) -> Option<(*mut Thread, *mut Thread)> {
+        let current_id = self.current;
+        let is_idle = current_id == self.idle;
+
+        if state == ThreadState::Blocked && self.current_thread().unparked {
+            self.current_thread().unparked = false;
+            return None;
+        }
+
+        let next_id = match self.ready.pop_front() {
+            Some(next_id) => next_id,
+            None if state == ThreadState::Ready => return None,
+            None => self.idle,
+        };
+
+        self.current_thread().state = state;
+        if state == ThreadState::Ready && !is_idle {
+            self.ready.push_back(current_id);
+        }
+
+        self.current = next_id;
+        let next = self.current_thread();
+        next.state = ThreadState::Running;
+        let next = next as *mut Thread;
+
+        let previous = if state == ThreadState::Finished {
+            let mut thread = self.threads.remove(&current_id).unwrap();
+            let previous = &mut *thread as *mut Thread;
+            self.finished.push(thread);
+            previous
+        } else {
+            &mut **self.threads.get_mut(&current_id).unwrap() as *mut Thread
+        };
+
+        Some((previous, next))
+    }
+}
+
+static SCHEDULER: Once<Mutex<Scheduler>> = Once::new();
+static SLICE_REMAINING: AtomicU32 = AtomicU32::new(TIME_SLICE_TICKS);
+static NEED_RESCHEDULE: AtomicBool = AtomicBool::new(false);
+
+// Threads only run on the boot processor; the others run executors.
+fn on_boot_cpu() -> bool {
+    smp::current_cpu() == 0
+}
+
+fn with_scheduler<R>(f: impl FnOnce(&mut Scheduler) -> R) -> Option<R> {
+    let scheduler = SCHEDULER.r#try()?;
+    Some(interrupts::without_interrupts(|| f(&mut scheduler.lock())))
+}
+
+/// Turns the running code into the first thread and creates the idle
+/// thread, which runs `idle` whenever nothing else is ready.
+pub(super) fn init(idle: fn()) {
+    SCHEDULER.call_once(|| {
+        let boot = Box::new(Thread {
+            id: ThreadId::new(),
+            name: String::from("main"),
+            state: ThreadState::Running,
+            stack: None,
+            rsp: 0,
+            fpu: FpuState::new(),
+            unparked: false,
+            entry: None,
+        });
+        let idle = Thread::new(String::from("idle"), Box::new(idle));
+
+        let current = boot.id;
+        let idle_id = idle.id;
+
+        let mut threads = BTreeMap::new();
+        threads.insert(boot.id, boot);
+        threads.insert(idle.id, idle);
+
+        Mutex::new(Scheduler {
+            threads,
+            ready: VecDeque::new(),
+            current,
+            idle: idle_id,
+            finished: Vec::new(),
+        })
+    });
+}
+
+pub(super) fn add_thread(name: String, entry: Box<dyn FnOnce() + Send>) -> ThreadId {
+    let thread = Thread::new(name, entry);
+    let id = thread.id;
+
+    with_scheduler(|scheduler| {
+        scheduler.threads.insert(id, thread);
+        scheduler.ready.push_back(id);
+    })
+    .expect("threads used before thread::init");
+
+    id
+}
+
+/// Switches away from the current thread, leaving it in `state`.
+///
+/// Must be called with interrupts disabled; each thread restores its own
+/// interrupt flag once it's resumed.
+pub(super) fn reschedule(state: ThreadState) {
+    assert!(
+        !interrupts::are_enabled(),
+        "rescheduling with interrupts enabled"
+    );
+
+    let scheduler = match SCHEDULER.r#try() {
+        Some(scheduler) if on_boot_cpu() => scheduler,
+        _ => return,
+    };
+
+    let (previous, next) = match scheduler.lock().switch_from_current(state) {
+        Some(threads) => threads,
+        None => return,
+    };
+
+    SLICE_REMAINING.store(TIME_SLICE_TICKS, Ordering::Relaxed);
+    NEED_RESCHEDULE.store(false, Ordering::Relaxed);
+
+    unsafe {
+        context::switch(&mut (*previous).rsp, &mut (*previous).fpu, (*next).rsp);
+    }
+}
+
+/// The first thing a new thread runs, on its own stack.
+#[no_mangle]
+extern "C" fn thread_entry() -> ! {
+    let (entry, fpu) = {
+        let mut scheduler = SCHEDULER.r#try().unwrap().lock();
+        let current = scheduler.current_thread();
+        (
+            current.entry.take().unwrap(),
+            &current.fpu as *const FpuState,
+        )
+    };
+
+    unsafe { (*fpu).restore() };
+    interrupts::enable();
+
+    entry();
+    super::exit()
+}
+
+pub(super) fn current() -> Option<ThreadId> {
+    if !on_boot_cpu() {
+        return None;
+    }
+
+    with_scheduler(|scheduler| scheduler.current)
+}
+
+/// Makes a blocked thread ready, or lets the next `park` return straight
+/// away if it isn't blocked yet.
+pub(super) fn unpark(id: ThreadId) {
+    with_scheduler(|scheduler| {
+        let idle_running = scheduler.current == scheduler.idle;
+
+        let thread = match scheduler.threads.get_mut(&id) {
+            Some(thread) => thread,
+            None => return,
+        };
+
+        if thread.state == ThreadState::Blocked {
+            thread.state = ThreadState::Ready;
+            scheduler.ready.push_back(id);
+
+            if idle_running {
+                NEED_RESCHEDULE.store(true, Ordering::Relaxed);
+            }
+        } else {
+            thread.unparked = true;
+        }
+    });
+}
+
+/// Frees the stacks of threads that have exited. Takes the memory locks, so
+/// must be called with interrupts enabled.
+pub(super) fn reap() {
+    let finished = with_scheduler(|scheduler| core::mem::take(&mut scheduler.finished));
+    drop(finished);
+}
+
+pub(super) fn has_ready_threads() -> bool {
+    if !on_boot_cpu() {
+        return false;
+    }
+
+    with_scheduler(|scheduler| !scheduler.ready.is_empty()).unwrap_or(false)
+}
+
+pub(super) fn threads() -> Vec<ThreadInfo> {
+    with_scheduler(|scheduler| {
+        scheduler
+            .threads
+            .values()
+            .map(|thread| thread.info())
+            .collect()
+    })
+    .unwrap_or_default()
+}
+
+/// Called on every timer interrupt to count down the running thread's time
+/// slice.
+pub(super) fn timer_tick() {
+    if SCHEDULER.r#try().is_none() {
+        return;
+    }
+
+    let remaining = SLICE_REMAINING.load(Ordering::Relaxed);
+    if remaining <= 1 {
+        SLICE_REMAINING.store(TIME_SLICE_TICKS, Ordering::Relaxed);
+        NEED_RESCHEDULE.store(true, Ordering::Relaxed);
+    } else {
+        SLICE_REMAINING.store(remaining - 1, Ordering::Relaxed);
+    }
+}
+
+/// Switches threads at the end of an interrupt handler if the running
+/// thread's time slice is up.
+pub(super) fn preempt_if_needed() {
+    if on_boot_cpu() && NEED_RESCHEDULE.swap(false, Ordering::Relaxed) {
+        reschedule(ThreadState::Ready);
+    }
+}
@@ -0,0 +1,24 @@
+use core::{
+    fmt::Display,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+#[derive(Copy, Clone, PartialOrd, PartialEq, Eq, Ord, Hash, Debug)]
+pub struct ThreadId(u64);
+
+impl ThreadId {
+    pub fn new() -> ThreadId {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+        ThreadId(NEXT_ID.fetch_add(1, Ordering::Relaxed))
+    }
+
+    pub fn as_u64(&self) -> u64 {
+        self.0
+    }
+}
+
+impl Display for ThreadId {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "Thread #{}", self.0)
+    }
+}
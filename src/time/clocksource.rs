@@ -0,0 +1,179 @@
+use core::{fmt::Display, time::Duration};
+
+use spin::Once;
+use x86_64::PhysAddr;
+
+use super::{hpet::Hpet, pit_time, tsc, Instant};
+use crate::{acpi, apic};
+
+const CALIBRATION_INTERVAL: Duration = Duration::from_millis(50);
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ClockSource {
+    Pit,
+    Hpet,
+    Tsc,
+}
+
+impl Display for ClockSource {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            ClockSource::Pit => write!(f, "PIT"),
+            ClockSource::Hpet => write!(f, "HPET"),
+            ClockSource::Tsc => write!(f, "TSC"),
+        }
+    }
+}
+
+/// A free-running counter, and the point on the PIT clock it took over
+/// from so time carries on from where the PIT left it.
+struct Clock {
+    source: ClockSource,
+    frequency: u64,
+    base_counter: u64,
+    base_time: Instant,
+}
+
+impl Clock {
+    fn new(source: ClockSource, frequency: u64) -> Self {
+        Self {
+            source,
+            frequency,
+            base_counter: read_counter(source),
+            base_time: pit_time(),
+        }
+    }
+
+    fn now(&self) -> Instant {
+        let elapsed = read_counter(self.source).wrapping_sub(self.base_counter) as u128;
+        let nanoseconds = elapsed * 1_000_000_000 / self.frequency as u128;
+
+        Instant::from_nanoseconds(self.base_time.as_nanoseconds() + nanoseconds as u64)
+    }
+}
+
+static HPET: Once<Option<Hpet>> = Once::new();
+static CLOCK: Once<Clock> = Once::new();
+
+fn read_counter(source: ClockSource) -> u64 {
+    match source {
+        ClockSource::Pit => 0,
+        ClockSource::Hpet => hpet().map_or(0, |hpet| hpet.counter()),
+        ClockSource::Tsc => tsc::read(),
+    }
+}
+
+fn hpet() -> Option<&'static Hpet> {
+    HPET.r#try().and_then(|hpet| hpet.as_ref())
+}
+
+/// Finds the HPET, calibrates the TSC and local APIC timer against the best
+/// reference available, and switches `time::now()` over to the best
+/// clocksource. Needs the PIT to be ticking.
+pub(super) fn init() {
+    let hpet = HPET.call_once(|| {
+        acpi::hpet().and_then(|info| Hpet::new(PhysAddr::new(info.base_address as u64)))
+    });
+
+    if let Some(hpet) = hpet {
+        println!(
+            "Timer: HPET at {} ({}-bit)",
+            Frequency(hpet.frequency()),
+            if hpet.is_64_bit() { 64 } else { 32 }
+        );
+    }
+
+    let invariant_tsc = tsc::is_invariant();
+    let tsc_frequency = measure_frequency(tsc::read);
+    println!(
+        "Timer: TSC at {}{}",
+        Frequency(tsc_frequency),
+        if invariant_tsc { " (invariant)" } else { "" }
+    );
+
+    if let Some(local_apic) = apic::local_apic() {
+        local_apic.start_timer(u32::MAX, None, false);
+        let apic_timer_frequency =
+            measure_frequency(|| (u32::MAX - local_apic.timer_count()) as u64);
+        local_apic.stop_timer();
+
+        apic::set_timer_frequency(apic_timer_frequency);
+        println!(
+            "Timer: local APIC timer at {} (bus clock / {})",
+            Frequency(apic_timer_frequency),
+            apic::TIMER_DIVISOR
+        );
+    }
+
+    let clock = if invariant_tsc {
+        Some(Clock::new(ClockSource::Tsc, tsc_frequency))
+    } else {
+        match hpet {
+            Some(hpet) if hpet.is_64_bit() => Some(Clock::new(ClockSource::Hpet, hpet.frequency())),
+            _ => None,
+        }
+    };
+
+    match clock {
+        Some(clock) => {
+            let clock = CLOCK.call_once(|| clock);
+            println!("Timer: using {} as the clocksource", clock.source);
+        }
+        None => println!("Timer: using PIT as the clocksource"),
+    }
+}
+
+pub fn clocksource() -> ClockSource {
+    CLOCK.r#try().map_or(ClockSource::Pit, |clock| clock.source)
+}
+
+pub(super) fn now() -> Option<Instant> {
+    CLOCK.r#try().map(|clock| clock.now())
+}
+
+/// Counts how fast `read` advances over `CALIBRATION_INTERVAL`, timed by the
+/// HPET if there is one and the PIT otherwise.
+fn measure_frequency(read: impl Fn() -> u64) -> u64 {
+    match hpet() {
+        Some(hpet) => {
+            let interval =
+                (CALIBRATION_INTERVAL.as_nanos() * hpet.frequency() as u128 / 1_000_000_000) as u64;
+
+            let reference_start = hpet.counter();
+            let start = read();
+            while hpet.counter().wrapping_sub(reference_start) < interval {}
+            let end = read();
+            let reference_end = hpet.counter();
+
+            let elapsed = reference_end.wrapping_sub(reference_start) as u128;
+            ((end - start) as u128 * hpet.frequency() as u128 / elapsed) as u64
+        }
+        None => {
+            // Start on a tick boundary, since the PIT clock only moves once
+            // per tick.
+            let tick = pit_time();
+            while pit_time() == tick {}
+
+            let reference_start = pit_time();
+            let start = read();
+            while pit_time().duration_since(reference_start) < CALIBRATION_INTERVAL {}
+            let end = read();
+            let elapsed = pit_time().duration_since(reference_start);
+
+            ((end - start) as u128 * 1_000_000_000 / elapsed.as_nanos()) as u64
+        }
+    }
+}
+
+struct Frequency(u64);
+
+impl Display for Frequency {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "{}.{:03} MHz",
+            self.0 / 1_000_000,
+            self.0 % 1_000_000 / 1000
+        )
+    }
+}
@@ -0,0 +1,73 @@
+use bit_field::BitField;
+use x86_64::PhysAddr;
+
+use crate::memory::{self, mmio::CacheMode, mmio::MmioMapping};
+
+const GENERAL_CAPABILITIES: usize = 0x000;
+const GENERAL_CONFIGURATION: usize = 0x010;
+const MAIN_COUNTER: usize = 0x0F0;
+
+const CAPABILITIES_64_BIT_COUNTER: usize = 13;
+const CONFIGURATION_ENABLE: usize = 0;
+
+// The specification caps the tick period at 100 ns.
+const MAX_PERIOD_FEMTOSECONDS: u64 = 100_000_000;
+
+const FEMTOSECONDS_PER_SECOND: u128 = 1_000_000_000_000_000;
+
+/// The High Precision Event Timer's free-running main counter. The
+/// comparators aren't used; interrupts still come from the PIT.
+pub struct Hpet {
+    mapping: MmioMapping,
+    period: u64,
+}
+
+impl Hpet {
+    /// Maps and starts the HPET at `physical_address`, or returns `None` if
+    /// it reports a nonsensical tick period.
+    pub fn new(physical_address: PhysAddr) -> Option<Self> {
+        let mut hpet = Self {
+            mapping: memory::map_mmio(physical_address, 0x400, CacheMode::Uncached),
+            period: 0,
+        };
+
+        hpet.period = hpet.read(GENERAL_CAPABILITIES).get_bits(32..64);
+        if hpet.period == 0 || hpet.period > MAX_PERIOD_FEMTOSECONDS {
+            return None;
+        }
+
+        let mut configuration = hpet.read(GENERAL_CONFIGURATION);
+        configuration.set_bit(CONFIGURATION_ENABLE, true);
+        hpet.write(GENERAL_CONFIGURATION, configuration);
+
+        Some(hpet)
+    }
+
+    fn read(&self, offset: usize) -> u64 {
+        unsafe { core::ptr::read_volatile(self.mapping.as_ptr::<u8>().add(offset).cast::<u64>()) }
+    }
+
+    fn write(&self, offset: usize, value: u64) {
+        unsafe {
+            core::ptr::write_volatile(
+                self.mapping.as_mut_ptr::<u8>().add(offset).cast::<u64>(),
+                value,
+            )
+        }
+    }
+
+    /// A 32-bit counter wraps every few minutes, too often to keep time
+    /// with.
+    pub fn is_64_bit(&self) -> bool {
+        self.read(GENERAL_CAPABILITIES)
+            .get_bit(CAPABILITIES_64_BIT_COUNTER)
+    }
+
+    pub fn counter(&self) -> u64 {
+        self.read(MAIN_COUNTER)
+    }
+
+    pub fn frequency(&self) -> u64 {
+        (FEMTOSECONDS_PER_SECOND / self.period as u128) as u64
+    }
+}
@@ -0,0 +1,83 @@
+mod clocksource;
+mod date_time;
+pub mod hpet;
+mod instant;
+pub mod pit;
+mod sleep;
+mod timeout;
+pub mod timer;
+pub mod tsc;
+mod wall_clock;
+
+pub use clocksource::{clocksource, ClockSource};
+pub use date_time::DateTime;
+pub use instant::Instant;
+pub use sleep::{sleep, sleep_until, Sleep};
+pub use timeout::{timeout, Elapsed};
+pub use wall_clock::{set_wall_clock, unix_time, wall_clock};
+
+use core::sync::atomic::{AtomicU64, Ordering};
+
+use spin::Once;
+
+use crate::{
+    interrupts::irq::{self, IrqRegistration, IrqResult},
+    thread,
+};
+
+pub const DEFAULT_TIMER_FREQUENCY: u32 = 1000;
+
+static TICKS: AtomicU64 = AtomicU64::new(0);
+
+// Counting input clock cycles rather than ticks keeps the clock exact, and
+// monotonic across changes to the PIT rate.
+static PIT_CYCLES: AtomicU64 = AtomicU64::new(0);
+
+static TIMER_IRQ: Once<IrqRegistration> = Once::new();
+
+/// Starts the PIT at `frequency` Hz and begins counting time, then
+/// calibrates the other timers and picks the best clocksource.
+pub fn init(frequency: u32) {
+    let divisor = pit::set_frequency(frequency);
+
+    TIMER_IRQ.call_once(|| {
+        irq::register_handler(pit::TIMER_IRQ, |_| {
+            tick();
+            IrqResult::Handled
+        })
+    });
+
+    println!(
+        "Timer: PIT at {} Hz (divisor {})",
+        pit::PIT_FREQUENCY / divisor as u64,
+        divisor
+    );
+
+    clocksource::init();
+}
+
+fn tick() {
+    TICKS.fetch_add(1, Ordering::Relaxed);
+    PIT_CYCLES.fetch_add(pit::divisor(), Ordering::Relaxed);
+
+    // Both allocate at times, which is fine under `IrqHandler`'s rules.
+    timer::wake_expired(now());
+    thread::timer_tick();
+}
+
+/// The number of timer interrupts since `init`.
+pub fn ticks() -> u64 {
+    TICKS.load(Ordering::Relaxed)
+}
+
+/// The current time on the monotonic clock.
+pub fn now() -> Instant {
+    clocksource::now().unwrap_or_else(pit_time)
+}
+
+/// Time as counted by the PIT interrupt alone, with a resolution of one
+/// tick.
+fn pit_time() -> Instant {
+    let cycles = PIT_CYCLES.load(Ordering::Relaxed) as u128;
+    Instant::from_nanoseconds((cycles * 1_000_000_000 / pit::PIT_FREQUENCY as u128) as u64)
+}
@@ -0,0 +1,42 @@
+use core::sync::atomic::{AtomicU64, Ordering};
+
+use x86_64::instructions::port::Port;
+
+/// The PIT's input clock, in Hz.
+pub const PIT_FREQUENCY: u64 = 1_193_182;
+
+pub const TIMER_IRQ: u8 = 0;
+
+const CHANNEL_0_DATA: u16 = 0x40;
+const MODE_COMMAND: u16 = 0x43;
+
+// Channel 0, low byte then high byte, mode 2 (rate generator), binary.
+const CHANNEL_0_RATE_GENERATOR: u8 = 0b0011_0100;
+
+static DIVISOR: AtomicU64 = AtomicU64::new(0);
+
+/// Programs channel 0 to fire IRQ 0 at approximately `frequency` Hz and
+/// returns the divisor actually used.
+pub fn set_frequency(frequency: u32) -> u16 {
+    let divisor = (PIT_FREQUENCY / frequency.max(1) as u64).max(1).min(0xFFFF) as u16;
+
+    let mut command: Port<u8> = Port::new(MODE_COMMAND);
+    let mut data: Port<u8> = Port::new(CHANNEL_0_DATA);
+
+    x86_64::instructions::interrupts::without_interrupts(|| {
+        unsafe {
+            command.write(CHANNEL_0_RATE_GENERATOR);
+            data.write(divisor as u8);
+            data.write((divisor >> 8) as u8);
+        }
+
+        DIVISOR.store(divisor as u64, Ordering::SeqCst);
+    });
+
+    divisor
+}
+
+/// The number of PIT input clock cycles between timer interrupts.
+pub fn divisor() -> u64 {
+    DIVISOR.load(Ordering::Relaxed)
+}
@@ -0,0 +1,78 @@
+use alloc::sync::Arc;
+use core::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use futures_util::task::AtomicWaker;
+
+use super::{
+    now,
+    timer::{self, TimerId},
+    Instant,
+};
+use crate::task::{self, WaitReason};
+
+/// Completes once the monotonic clock reaches its deadline. Precision is
+/// limited to one timer tick.
+pub struct Sleep {
+    deadline: Instant,
+    timer: Option<(TimerId, Arc<AtomicWaker>)>,
+}
+
+pub fn sleep(duration: Duration) -> Sleep {
+    sleep_until(now() + duration)
+}
+
+pub fn sleep_until(deadline: Instant) -> Sleep {
+    Sleep {
+        deadline,
+        timer: None,
+    }
+}
+
+impl Sleep {
+    pub fn deadline(&self) -> Instant {
+        self.deadline
+    }
+}
+
+impl Future for Sleep {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if now() >= self.deadline {
+            return Poll::Ready(());
+        }
+
+        match &self.timer {
+            Some((_, waker)) => waker.register(cx.waker()),
+            None => {
+                let waker = Arc::new(AtomicWaker::new());
+                waker.register(cx.waker());
+                let timer = timer::add_timer(self.deadline, waker.clone());
+                self.timer = Some((timer, waker));
+            }
+        }
+
+        // The deadline may have passed between the check and registering.
+        if now() >= self.deadline {
+            Poll::Ready(())
+        } else {
+            task::set_wait_reason(WaitReason::Sleep(self.deadline));
+            Poll::Pending
+        }
+    }
+}
+
+impl Drop for Sleep {
+    fn drop(&mut self) {
+        // Otherwise a sleep dropped early, e.g. by a timeout whose future
+        // won, would stay queued until its deadline.
+        if let Some((timer, _)) = self.timer {
+            timer::cancel_timer(timer);
+        }
+    }
+}
@@ -0,0 +1,29 @@
+use core::{fmt::Display, future::Future, time::Duration};
+
+use futures_util::{
+    future::{select, Either},
+    pin_mut,
+};
+
+use super::sleep;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Elapsed;
+
+impl Display for Elapsed {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "timed out")
+    }
+}
+
+/// Runs `future` to completion unless `duration` passes first, in which
+/// case it's dropped and `Elapsed` returned.
+pub async fn timeout<F: Future>(future: F, duration: Duration) -> Result<F::Output, Elapsed> {
+    let sleep = sleep(duration);
+    pin_mut!(future);
+
+    match select(future, sleep).await {
+        Either::Left((output, _)) => Ok(output),
+        Either::Right(_) => Err(Elapsed),
+    }
+}
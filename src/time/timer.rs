@@ -0,0 +1,62 @@
+use alloc::{collections::BTreeMap, sync::Arc};
+use core::sync::atomic::{AtomicU64, Ordering};
+
+use futures_util::task::AtomicWaker;
+use lazy_static::lazy_static;
+use spin::Mutex;
+use x86_64::instructions::interrupts;
+
+use super::Instant;
+
+/// Identifies a pending timer, ordered by deadline. The waker is shared
+/// with the future waiting on it, so re-polling updates the waker in place
+/// instead of queueing another timer.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct TimerId {
+    deadline: Instant,
+    id: u64,
+}
+
+lazy_static! {
+    static ref TIMERS: Mutex<BTreeMap<TimerId, Arc<AtomicWaker>>> = Mutex::new(BTreeMap::new());
+}
+
+/// Arranges for `waker` to be woken from the timer interrupt once
+/// `deadline` has passed, unless the timer is cancelled first.
+pub fn add_timer(deadline: Instant, waker: Arc<AtomicWaker>) -> TimerId {
+    static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+    let timer = TimerId {
+        deadline,
+        id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+    };
+
+    interrupts::without_interrupts(|| TIMERS.lock().insert(timer, waker));
+    timer
+}
+
+/// Removes a timer that hasn't expired yet; does nothing if it has.
+pub fn cancel_timer(timer: TimerId) {
+    interrupts::without_interrupts(|| TIMERS.lock().remove(&timer));
+}
+
+/// Wakes everything whose deadline is at or before `now`. Called from the
+/// timer interrupt.
+pub(super) fn wake_expired(now: Instant) {
+    let mut timers = TIMERS.lock();
+
+    loop {
+        let timer = match timers.keys().next() {
+            Some(&timer) if timer.deadline <= now => timer,
+            _ => break,
+        };
+
+        if let Some(waker) = timers.remove(&timer) {
+            waker.wake();
+        }
+    }
+}
+
+pub fn pending_timers() -> usize {
+    interrupts::without_interrupts(|| TIMERS.lock().len())
+}
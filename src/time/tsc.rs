@@ -0,0 +1,17 @@
+use core::arch::x86_64::{__cpuid, _rdtsc};
+
+/// Whether the TSC ticks at a constant rate regardless of power states,
+/// which is what makes it usable as a clock.
+pub fn is_invariant() -> bool {
+    let max_extended_leaf = unsafe { __cpuid(0x8000_0000) }.eax;
+    if max_extended_leaf < 0x8000_0007 {
+        return false;
+    }
+
+    let power_management = unsafe { __cpuid(0x8000_0007) };
+    power_management.edx & (1 << 8) != 0
+}
+
+pub fn read() -> u64 {
+    unsafe { _rdtsc() }
+}
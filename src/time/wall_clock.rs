@@ -0,0 +1,27 @@
+use core::time::Duration;
+
+use spin::Mutex;
+use x86_64::instructions::interrupts;
+
+use super::{now, DateTime, Instant};
+
+/// The Unix time at a point on the monotonic clock, as last read from the
+/// RTC.
+static REFERENCE: Mutex<Option<(Duration, Instant)>> = Mutex::new(None);
+
+/// Records that it was `unix_time` at `instant` on the monotonic clock.
+pub fn set_wall_clock(unix_time: Duration, instant: Instant) {
+    interrupts::without_interrupts(|| *REFERENCE.lock() = Some((unix_time, instant)));
+}
+
+/// The time since the Unix epoch, or `None` until a real-time clock has
+/// been read. Between RTC reads it advances with the monotonic clock.
+pub fn unix_time() -> Option<Duration> {
+    let reference = interrupts::without_interrupts(|| *REFERENCE.lock());
+
+    reference.map(|(unix_time, instant)| unix_time + now().duration_since(instant))
+}
+
+pub fn wall_clock() -> Option<DateTime> {
+    unix_time().map(|unix_time| DateTime::from_unix_seconds(unix_time.as_secs()))
+}
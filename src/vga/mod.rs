@@ -9,16 +9,42 @@ use colour::Colour;
 use colour_code::ColourCode;
 use writer::Writer;
 
+use x86_64::PhysAddr;
+
+use crate::memory::{
+    self,
+    mmio::{CacheMode, MmioMapping},
+};
+
+const TEXT_BUFFER_ADDRESS: u64 = 0xb8000;
+
 pub struct Vga {
     pub writer: Writer,
+    mapping: Option<MmioMapping>,
 }
 
 impl Vga {
+    /// Writes through the bootloader's physical memory map, which is all
+    /// there is before `memory::init`; see `map_write_combining`.
     pub fn new(physical_base: u64) -> Self {
         Vga {
             writer: Writer::new(0, ColourCode::new(Colour::White, Colour::Black), unsafe {
-                &mut *((physical_base + 0xb8000) as *mut Buffer)
+                &mut *((physical_base + TEXT_BUFFER_ADDRESS) as *mut Buffer)
             }),
+            mapping: None,
         }
     }
+
+    /// Moves the text buffer onto a write-combining mapping of its own.
+    pub fn map_write_combining(&mut self) {
+        let mapping = memory::map_mmio(
+            PhysAddr::new(TEXT_BUFFER_ADDRESS),
+            core::mem::size_of::<Buffer>(),
+            CacheMode::WriteCombining,
+        );
+
+        self.writer
+            .set_buffer(unsafe { &mut *mapping.as_mut_ptr::<Buffer>() });
+        self.mapping = Some(mapping);
+    }
 }
@@ -22,6 +22,10 @@ impl Writer {
         }
     }
 
+    pub fn set_buffer(&mut self, buffer: &'static mut Buffer) {
+        self.buffer = buffer;
+    }
+
     pub fn write_byte(&mut self, byte: u8) {
         match byte {
             b'\n' => self.new_line(),
@@ -0,0 +1,84 @@
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![test_runner(panda::test_runner)]
+
+use core::time::Duration;
+
+use bootloader::{entry_point, BootInfo};
+use panda::time::{hpet::Hpet, tsc, ClockSource};
+use panda::*;
+use x86_64::PhysAddr;
+
+const TICKS: u64 = 100;
+
+entry_point!(main);
+
+fn main(boot_info: &'static BootInfo) -> ! {
+    serial_print!("testing clocksource selection and calibration... ");
+
+    gdt::init();
+    interrupts::init();
+    pic::init();
+    memory::init(boot_info);
+    gdt::init_stacks();
+    device::init();
+    acpi::init();
+    apic::init();
+    time::init(time::DEFAULT_TIMER_FREQUENCY);
+
+    // An invariant TSC beats the HPET, which is only usable as a clock if
+    // its counter is 64 bits wide.
+    let hpet_64_bit = acpi::hpet()
+        .and_then(|info| Hpet::new(PhysAddr::new(info.base_address as u64)))
+        .map_or(false, |hpet| hpet.is_64_bit());
+    let expected = if tsc::is_invariant() {
+        ClockSource::Tsc
+    } else if hpet_64_bit {
+        ClockSource::Hpet
+    } else {
+        ClockSource::Pit
+    };
+    assert_eq!(time::clocksource(), expected);
+
+    assert!(apic::timer_frequency().map_or(false, |frequency| frequency > 0));
+
+    let mut previous = time::now();
+    for _ in 0..10_000 {
+        let now = time::now();
+        assert!(now >= previous);
+        previous = now;
+    }
+
+    // The clocksource took over from the PIT and still keeps pace with it.
+    let tick = time::ticks();
+    while time::ticks() == tick {}
+
+    let start = time::now();
+    let start_tick = time::ticks();
+    while time::ticks() - start_tick < TICKS {}
+    let elapsed = start.elapsed();
+
+    let expected = Duration::from_millis(TICKS * 1000 / time::DEFAULT_TIMER_FREQUENCY as u64);
+    let tolerance = expected / 10;
+    assert!(
+        elapsed >= expected - tolerance && elapsed <= expected + tolerance,
+        "{} PIT ticks took {:?} on the {}",
+        TICKS,
+        elapsed,
+        time::clocksource()
+    );
+
+    serial_println!("[ok]");
+
+    qemu::exit_success();
+    loop {}
+}
+
+#[panic_handler]
+fn panic(info: &core::panic::PanicInfo) -> ! {
+    serial_println!("[failed]");
+    serial_println!("{}", info);
+    qemu::exit_failure();
+    loop {}
+}
@@ -0,0 +1,88 @@
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![test_runner(panda::test_runner)]
+
+use bootloader::{entry_point, BootInfo};
+use panda::memory::{
+    self,
+    dma::{DmaBuffer, DmaConstraints},
+    frame_allocator::FRAME_SIZE,
+};
+use panda::*;
+
+const FOUR_GIB: u64 = 0x1_0000_0000;
+const LEN: usize = 3 * FRAME_SIZE as usize + 100;
+const ALIGNMENT: usize = 64 * 1024;
+
+entry_point!(main);
+
+fn main(boot_info: &'static BootInfo) -> ! {
+    serial_print!("testing DMA buffer allocation... ");
+
+    gdt::init();
+    interrupts::init();
+    memory::init(boot_info);
+
+    let allocated = allocated_frames();
+    let constraints = DmaConstraints {
+        alignment: ALIGNMENT,
+        below_4gib: true,
+    };
+
+    let mut buffer = DmaBuffer::allocate(LEN, constraints).expect("DMA allocation failed");
+    let physical = buffer.physical_address().as_u64();
+
+    assert_eq!(buffer.len(), LEN);
+    assert_eq!(physical % ALIGNMENT as u64, 0);
+    assert!(physical + LEN as u64 <= FOUR_GIB);
+    assert_eq!(allocated_frames(), allocated + 4);
+    assert!(buffer.as_slice().iter().all(|&byte| byte == 0));
+
+    // The CPU's view is physically contiguous, so the device sees the same
+    // bytes at the same offsets.
+    for offset in (0..LEN as u64).step_by(FRAME_SIZE as usize) {
+        assert_eq!(
+            memory::translate(buffer.virtual_address() + offset).map(|address| address.as_u64()),
+            Some(physical + offset)
+        );
+    }
+
+    for (index, byte) in buffer.as_mut_slice().iter_mut().enumerate() {
+        *byte = index as u8;
+    }
+    let last = memory::physical_to_virtual_address(buffer.physical_address() + LEN as u64 - 1);
+    assert_eq!(unsafe { *last.as_ptr::<u8>() }, (LEN - 1) as u8);
+
+    drop(buffer);
+    assert_eq!(allocated_frames(), allocated);
+
+    // Reusing the frames just dirtied still hands out a zeroed buffer.
+    let buffer = DmaBuffer::allocate(LEN, constraints).expect("DMA allocation failed");
+    assert!(buffer.as_slice().iter().all(|&byte| byte == 0));
+    drop(buffer);
+
+    let buffer = DmaBuffer::allocate(1, DmaConstraints::default()).expect("DMA allocation failed");
+    assert_eq!(buffer.physical_address().as_u64() % FRAME_SIZE, 0);
+    assert_eq!(allocated_frames(), allocated + 1);
+    drop(buffer);
+
+    assert_eq!(allocated_frames(), allocated);
+
+    serial_println!("[ok]");
+
+    qemu::exit_success();
+    loop {}
+}
+
+fn allocated_frames() -> usize {
+    memory::with_frame_allocator(|frame_allocator| frame_allocator.allocated_frames())
+}
+
+#[panic_handler]
+fn panic(info: &core::panic::PanicInfo) -> ! {
+    serial_println!("[failed]");
+    serial_println!("{}", info);
+    qemu::exit_failure();
+    loop {}
+}
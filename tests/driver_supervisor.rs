@@ -0,0 +1,112 @@
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![test_runner(panda::test_runner)]
+
+extern crate alloc;
+
+use core::{
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Duration,
+};
+
+use bootloader::{entry_point, BootInfo};
+use panda::device::{
+    device_manager,
+    drivers::{supervise, DriverError, RestartPolicy},
+    DeviceStatus,
+};
+use panda::task::{Executor, Priority, Task};
+use panda::*;
+
+const FAILURES: usize = 2;
+
+static FLAKY_STARTS: AtomicUsize = AtomicUsize::new(0);
+static BROKEN_STARTS: AtomicUsize = AtomicUsize::new(0);
+
+async fn flaky_driver() -> Result<(), DriverError> {
+    if FLAKY_STARTS.fetch_add(1, Ordering::Relaxed) < FAILURES {
+        return Err(DriverError::MissingResource("flaky resource"));
+    }
+
+    Ok(())
+}
+
+async fn broken_driver() -> Result<(), DriverError> {
+    BROKEN_STARTS.fetch_add(1, Ordering::Relaxed);
+    Err(DriverError::MissingResource("broken resource"))
+}
+
+entry_point!(main);
+
+fn main(boot_info: &'static BootInfo) -> ! {
+    serial_print!("testing that failed drivers are isolated and restarted... ");
+
+    gdt::init();
+    interrupts::init();
+    pic::init();
+    memory::init(boot_info);
+    gdt::init_stacks();
+    device::init();
+    acpi::init();
+    apic::init();
+    time::init(time::DEFAULT_TIMER_FREQUENCY);
+
+    let mut acpi_devices = acpi::devices();
+    let mut add_device = || {
+        let acpi_address = acpi_devices.next().expect("not enough ACPI devices");
+        device_manager()
+            .upgrade()
+            .add_acpi_device(acpi_address, None)
+    };
+    let (flaky, broken) = (add_device(), add_device());
+    assert_ne!(flaky, broken);
+
+    let policy = RestartPolicy {
+        max_restarts: 5,
+        initial_backoff: Duration::from_millis(10),
+        max_backoff: Duration::from_millis(15),
+    };
+
+    let mut executor = Executor::new();
+    executor.spawn(Task::new(
+        "flaky",
+        Priority::Background,
+        supervise(flaky, "Flaky", policy, flaky_driver),
+    ));
+    executor.spawn(Task::new(
+        "broken",
+        Priority::Background,
+        supervise(broken, "Broken", RestartPolicy::NEVER, broken_driver),
+    ));
+
+    let start = time::now();
+    executor.run_to_completion();
+
+    // Restarted after waiting 10 ms, then 15 ms, until it succeeded.
+    assert_eq!(FLAKY_STARTS.load(Ordering::Relaxed), FAILURES + 1);
+    assert!(start.elapsed() >= Duration::from_millis(25));
+    assert!(matches!(
+        device_manager().status(&flaky),
+        Some(DeviceStatus::Stopped)
+    ));
+
+    assert_eq!(BROKEN_STARTS.load(Ordering::Relaxed), 1);
+    assert!(matches!(
+        device_manager().status(&broken),
+        Some(DeviceStatus::Failed(DriverError::MissingResource(_)))
+    ));
+
+    serial_println!("[ok]");
+
+    qemu::exit_success();
+    loop {}
+}
+
+#[panic_handler]
+fn panic(info: &core::panic::PanicInfo) -> ! {
+    serial_println!("[failed]");
+    serial_println!("{}", info);
+    qemu::exit_failure();
+    loop {}
+}
@@ -0,0 +1,113 @@
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![test_runner(panda::test_runner)]
+
+extern crate alloc;
+
+use core::{
+    future::Future,
+    pin::Pin,
+    sync::atomic::{AtomicUsize, Ordering},
+    task::{Context, Poll},
+};
+
+use bootloader::{entry_point, BootInfo};
+use panda::task::{queue_latency, Executor, Priority, Task};
+use panda::*;
+
+const BACKGROUND_TASKS: usize = 100;
+const POLLS_PER_TASK: usize = 10;
+
+static BACKGROUND_POLLS: AtomicUsize = AtomicUsize::new(0);
+static BACKGROUND_DONE: AtomicUsize = AtomicUsize::new(0);
+static POLLS_BEFORE_INTERACTIVE: AtomicUsize = AtomicUsize::new(usize::MAX);
+
+/// Wakes itself until it has been polled `POLLS_PER_TASK` times.
+struct Chore {
+    polls: usize,
+}
+
+impl Future for Chore {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        BACKGROUND_POLLS.fetch_add(1, Ordering::Relaxed);
+        self.polls += 1;
+
+        if self.polls == POLLS_PER_TASK {
+            BACKGROUND_DONE.fetch_add(1, Ordering::Relaxed);
+            return Poll::Ready(());
+        }
+
+        cx.waker().wake_by_ref();
+        Poll::Pending
+    }
+}
+
+/// Always ready to run again, so only starvation protection lets the
+/// background tasks make progress.
+struct Impatient;
+
+impl Future for Impatient {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let background_polls = BACKGROUND_POLLS.load(Ordering::Relaxed);
+        let _ = POLLS_BEFORE_INTERACTIVE.compare_exchange(
+            usize::MAX,
+            background_polls,
+            Ordering::Relaxed,
+            Ordering::Relaxed,
+        );
+
+        if BACKGROUND_DONE.load(Ordering::Relaxed) == BACKGROUND_TASKS {
+            return Poll::Ready(());
+        }
+
+        cx.waker().wake_by_ref();
+        Poll::Pending
+    }
+}
+
+entry_point!(main);
+
+fn main(boot_info: &'static BootInfo) -> ! {
+    serial_print!("testing that urgent tasks run first and background tasks still finish... ");
+
+    panda::gdt::init();
+    panda::interrupts::init();
+    panda::memory::init(boot_info);
+
+    let mut executor = Executor::new();
+    for _ in 0..BACKGROUND_TASKS {
+        executor.spawn(Task::new("chore", Priority::Background, Chore { polls: 0 }));
+    }
+    executor.spawn(Task::new("impatient", Priority::Interactive, Impatient));
+
+    executor.run_to_completion();
+
+    // Queued after every background task, but polled before any of them.
+    assert_eq!(POLLS_BEFORE_INTERACTIVE.load(Ordering::Relaxed), 0);
+    assert_eq!(BACKGROUND_DONE.load(Ordering::Relaxed), BACKGROUND_TASKS);
+
+    let latency = queue_latency();
+    assert_eq!(
+        latency[Priority::Background.index()].polls as usize,
+        BACKGROUND_TASKS * POLLS_PER_TASK
+    );
+    assert!(latency[Priority::Interactive.index()].polls > 0);
+
+    serial_println!("[ok]");
+
+    qemu::exit_success();
+    loop {}
+}
+
+#[panic_handler]
+fn panic(info: &core::panic::PanicInfo) -> ! {
+    serial_println!("[failed]");
+    serial_println!("{}", info);
+    qemu::exit_failure();
+    loop {}
+}
@@ -0,0 +1,90 @@
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![test_runner(panda::test_runner)]
+
+extern crate alloc;
+
+use core::{
+    future::Future,
+    pin::Pin,
+    sync::atomic::{AtomicUsize, Ordering},
+    task::{Context, Poll},
+};
+
+use bootloader::{entry_point, BootInfo};
+use panda::task::{Executor, Priority, Task};
+use panda::*;
+
+const TASKS: usize = 10_000;
+const POLLS_PER_TASK: usize = 10;
+const WAKES_PER_POLL: usize = 5;
+
+static POLLS: AtomicUsize = AtomicUsize::new(0);
+static COMPLETED: AtomicUsize = AtomicUsize::new(0);
+
+/// Wakes itself several times on every poll, as a burst of IRQs might,
+/// until it has been polled `POLLS_PER_TASK` times.
+struct Restless {
+    polls: usize,
+}
+
+impl Future for Restless {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        POLLS.fetch_add(1, Ordering::Relaxed);
+        self.polls += 1;
+
+        if self.polls == POLLS_PER_TASK {
+            COMPLETED.fetch_add(1, Ordering::Relaxed);
+            return Poll::Ready(());
+        }
+
+        for _ in 0..WAKES_PER_POLL {
+            cx.waker().wake_by_ref();
+        }
+
+        Poll::Pending
+    }
+}
+
+entry_point!(main);
+
+fn main(boot_info: &'static BootInfo) -> ! {
+    serial_print!("testing that the executor survives thousands of tasks and wakeups... ");
+
+    panda::gdt::init();
+    panda::interrupts::init();
+    panda::memory::init(boot_info);
+
+    let mut executor = Executor::new();
+    for _ in 0..TASKS {
+        executor.spawn(Task::new(
+            "restless",
+            Priority::Background,
+            Restless { polls: 0 },
+        ));
+    }
+    assert_eq!(executor.queued_tasks(), TASKS);
+
+    executor.run_to_completion();
+
+    assert_eq!(COMPLETED.load(Ordering::Relaxed), TASKS);
+    // Duplicate wakeups must not turn into extra polls.
+    assert_eq!(POLLS.load(Ordering::Relaxed), TASKS * POLLS_PER_TASK);
+    assert_eq!(executor.queued_tasks(), 0);
+
+    serial_println!("[ok]");
+
+    qemu::exit_success();
+    loop {}
+}
+
+#[panic_handler]
+fn panic(info: &core::panic::PanicInfo) -> ! {
+    serial_println!("[failed]");
+    serial_println!("{}", info);
+    qemu::exit_failure();
+    loop {}
+}
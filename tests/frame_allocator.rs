@@ -0,0 +1,119 @@
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![test_runner(panda::test_runner)]
+
+extern crate alloc;
+
+use alloc::vec::Vec;
+use bootloader::{entry_point, BootInfo};
+use panda::memory::{self, frame_allocator::FRAME_SIZE};
+use panda::*;
+use x86_64::{structures::paging::PhysFrame, PhysAddr};
+
+const SIXTEEN_MIB: u64 = 16 * 1024 * 1024;
+
+entry_point!(main);
+
+fn main(boot_info: &'static BootInfo) -> ! {
+    serial_print!("testing contiguous and aligned frame allocation... ");
+
+    gdt::init();
+    interrupts::init();
+    memory::init(boot_info);
+
+    // Allocated up front, so a slab refill doesn't skew the frame counts.
+    let mut frames = Vec::with_capacity(32);
+
+    let (allocated, free) = memory::with_frame_allocator(|frame_allocator| {
+        (
+            frame_allocator.allocated_frames(),
+            frame_allocator.free_frames(),
+        )
+    });
+
+    let (aligned, unaligned, low) = memory::with_frame_allocator(|frame_allocator| {
+        (
+            frame_allocator
+                .allocate_contiguous(8, 16)
+                .expect("no 8 frames aligned to 16"),
+            frame_allocator
+                .allocate_contiguous(3, 1)
+                .expect("no 3 contiguous frames"),
+            frame_allocator
+                .allocate_contiguous_below(4, 4, PhysAddr::new(SIXTEEN_MIB))
+                .expect("no 4 frames below 16 MiB"),
+        )
+    });
+
+    assert_eq!(aligned.start_address().as_u64() % (16 * FRAME_SIZE), 0);
+    assert_eq!(low.start_address().as_u64() % (4 * FRAME_SIZE), 0);
+    assert!(low.start_address().as_u64() + 4 * FRAME_SIZE <= SIXTEEN_MIB);
+
+    let ranges: [(PhysFrame, usize); 3] = [(aligned, 8), (unaligned, 3), (low, 4)];
+    for (index, &(start, count)) in ranges.iter().enumerate() {
+        for &(other, other_count) in &ranges[index + 1..] {
+            assert!(!overlaps(start, count, other, other_count));
+        }
+    }
+
+    memory::with_frame_allocator(|frame_allocator| {
+        assert_eq!(frame_allocator.allocated_frames(), allocated + 15);
+        assert_eq!(frame_allocator.free_frames(), free - 15);
+    });
+
+    // Single frames must come from outside every contiguous block.
+    for _ in 0..32 {
+        frames.push(memory::allocate_frame().expect("out of frames"));
+    }
+    for &frame in &frames {
+        assert!(ranges
+            .iter()
+            .all(|&(start, count)| !overlaps(start, count, frame, 1)));
+    }
+
+    unsafe {
+        for frame in frames.drain(..) {
+            memory::deallocate_frame(frame);
+        }
+
+        memory::with_frame_allocator(|frame_allocator| {
+            for &(start, count) in &ranges {
+                frame_allocator.deallocate_contiguous(start, count);
+            }
+        });
+    }
+
+    memory::with_frame_allocator(|frame_allocator| {
+        assert_eq!(frame_allocator.allocated_frames(), allocated);
+        assert_eq!(frame_allocator.free_frames(), free);
+
+        // The freed block is reusable as a whole.
+        let again = frame_allocator
+            .allocate_contiguous(8, 16)
+            .expect("freed frames weren't returned to the pool");
+        unsafe { frame_allocator.deallocate_contiguous(again, 8) };
+    });
+
+    serial_println!("[ok]");
+
+    qemu::exit_success();
+    loop {}
+}
+
+fn overlaps(a: PhysFrame, a_count: usize, b: PhysFrame, b_count: usize) -> bool {
+    let a_start = a.start_address().as_u64();
+    let a_end = a_start + a_count as u64 * FRAME_SIZE;
+    let b_start = b.start_address().as_u64();
+    let b_end = b_start + b_count as u64 * FRAME_SIZE;
+
+    a_start < b_end && b_start < a_end
+}
+
+#[panic_handler]
+fn panic(info: &core::panic::PanicInfo) -> ! {
+    serial_println!("[failed]");
+    serial_println!("{}", info);
+    qemu::exit_failure();
+    loop {}
+}
@@ -0,0 +1,43 @@
+#![no_std]
+#![no_main]
+#![feature(core_intrinsics)]
+#![feature(custom_test_frameworks)]
+#![test_runner(panda::test_runner)]
+
+use core::sync::atomic::Ordering;
+
+use panda::interrupts::double_fault::DOUBLE_FAULT_COUNT;
+use panda::interrupts::invalid_opcode::INVALID_OPCODE_COUNT;
+use panda::*;
+
+#[no_mangle]
+pub extern "C" fn _start() -> ! {
+    serial_print!("testing that an invalid opcode is caught by its own handler... ");
+
+    panda::gdt::init();
+    panda::interrupts::init();
+
+    // Compiles to `ud2`.
+    core::intrinsics::abort();
+}
+
+#[panic_handler]
+fn panic(info: &core::panic::PanicInfo) -> ! {
+    let invalid_opcode_count = INVALID_OPCODE_COUNT.load(Ordering::Acquire);
+    let double_fault_count = DOUBLE_FAULT_COUNT.load(Ordering::Acquire);
+
+    if invalid_opcode_count == 1 && double_fault_count == 0 {
+        serial_println!("[ok]");
+        qemu::exit_success();
+    } else {
+        serial_println!(
+            "[failed] ({} invalid opcode, {} double fault exceptions)",
+            invalid_opcode_count,
+            double_fault_count
+        );
+        serial_println!("{}", info);
+        qemu::exit_failure();
+    }
+
+    loop {}
+}
@@ -0,0 +1,149 @@
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![test_runner(panda::test_runner)]
+
+use bootloader::{entry_point, BootInfo};
+use panda::memory::{self, mmio::CacheMode};
+use panda::*;
+use x86_64::{
+    structures::paging::{Page, PageTableFlags, Size2MiB},
+    PhysAddr,
+};
+
+const TWO_MIB: u64 = 2 * 1024 * 1024;
+
+// The I/O APIC, HPET and local APIC live here on q35; the test only maps
+// the window, it never touches the registers.
+const DEVICE_WINDOW: u64 = 0xFEC0_0000;
+const VGA_TEXT_BUFFER: u64 = 0xB8000;
+
+entry_point!(main);
+
+fn main(boot_info: &'static BootInfo) -> ! {
+    serial_print!("testing virtual ranges, MMIO and huge page mappings... ");
+
+    gdt::init();
+    interrupts::init();
+    memory::init(boot_info);
+
+    test_virtual_ranges();
+    test_huge_page_reuses_freed_tables();
+    test_mmio_huge_pages();
+    test_mmio_write_combining();
+
+    serial_println!("[ok]");
+
+    qemu::exit_success();
+    loop {}
+}
+
+fn test_virtual_ranges() {
+    let first = memory::allocate_virtual_range(0x3000, 0x1000).expect("no virtual range");
+    let aligned = memory::allocate_virtual_range(0x1000, 0x10000).expect("no virtual range");
+
+    assert_eq!(aligned.as_u64() % 0x10000, 0);
+    assert!(first + 0x3000u64 <= aligned || aligned + 0x1000u64 <= first);
+
+    memory::deallocate_virtual_range(aligned, 0x1000);
+    memory::deallocate_virtual_range(first, 0x3000);
+
+    // The freed ranges merged back, so first fit finds the same spot.
+    assert_eq!(memory::allocate_virtual_range(0x3000, 0x1000), Some(first));
+    memory::deallocate_virtual_range(first, 0x3000);
+}
+
+fn test_huge_page_reuses_freed_tables() {
+    let start = memory::allocate_virtual_range(TWO_MIB, TWO_MIB).expect("no virtual range");
+    let flags = PageTableFlags::PRESENT | PageTableFlags::WRITABLE | PageTableFlags::NO_EXECUTE;
+    let small_page = Page::containing_address(start);
+    let huge_page = Page::<Size2MiB>::from_start_address(start).unwrap();
+
+    // Leaves behind any level 3 table the range needs, which is never freed.
+    unsafe {
+        memory::map_page(small_page, flags);
+        memory::unmap_page(small_page);
+    }
+    let allocated = allocated_frames();
+
+    unsafe {
+        memory::map_page(small_page, flags);
+        *start.as_mut_ptr::<u64>() = 0x1234_5678;
+        assert_eq!(*start.as_ptr::<u64>(), 0x1234_5678);
+        memory::unmap_page(small_page);
+    }
+    assert!(!memory::is_mapped(start));
+    // The level 1 and 2 tables went with the page.
+    assert_eq!(allocated_frames(), allocated);
+
+    // Only possible once the level 1 table the 4 KiB page left is gone.
+    unsafe { memory::map_huge_page(huge_page, flags) };
+    let physical = memory::translate(start).expect("huge page isn't mapped");
+    assert_eq!(physical.as_u64() % TWO_MIB, 0);
+    assert_eq!(
+        memory::translate(start + 0x12_3456u64),
+        Some(physical + 0x12_3456u64)
+    );
+
+    let last = start + (TWO_MIB - 8);
+    unsafe {
+        *last.as_mut_ptr::<u64>() = 0x8765_4321;
+        assert_eq!(*last.as_ptr::<u64>(), 0x8765_4321);
+        memory::unmap_huge_page(huge_page);
+    }
+    assert!(!memory::is_mapped(start));
+    assert!(!memory::is_mapped(last));
+    assert_eq!(allocated_frames(), allocated);
+
+    memory::deallocate_virtual_range(start, TWO_MIB);
+}
+
+fn test_mmio_huge_pages() {
+    let mapping = memory::map_mmio(
+        PhysAddr::new(DEVICE_WINDOW),
+        2 * TWO_MIB as usize,
+        CacheMode::Uncached,
+    );
+    let start = mapping.virtual_start();
+
+    assert_eq!(mapping.physical_start(), PhysAddr::new(DEVICE_WINDOW));
+    assert_eq!(start.as_u64() % TWO_MIB, 0);
+    assert_eq!(
+        memory::translate(start + TWO_MIB + 0x1234u64),
+        Some(PhysAddr::new(DEVICE_WINDOW + TWO_MIB + 0x1234))
+    );
+
+    drop(mapping);
+    assert!(!memory::is_mapped(start));
+    assert!(!memory::is_mapped(start + TWO_MIB));
+}
+
+fn test_mmio_write_combining() {
+    // Unaligned and spanning two pages.
+    let physical = PhysAddr::new(VGA_TEXT_BUFFER + 0x10);
+    let mapping = memory::map_mmio(physical, 0x1000, CacheMode::WriteCombining);
+    let start = mapping.virtual_start();
+
+    assert_eq!(start.as_u64() % 0x1000, 0x10);
+    assert_eq!(memory::translate(start), Some(physical));
+    assert_eq!(
+        memory::translate(start + 0xFF8u64),
+        Some(physical + 0xFF8u64)
+    );
+
+    drop(mapping);
+    assert!(!memory::is_mapped(start));
+    assert!(!memory::is_mapped(start + 0x1000u64));
+}
+
+fn allocated_frames() -> usize {
+    memory::with_frame_allocator(|frame_allocator| frame_allocator.allocated_frames())
+}
+
+#[panic_handler]
+fn panic(info: &core::panic::PanicInfo) -> ! {
+    serial_println!("[failed]");
+    serial_println!("{}", info);
+    qemu::exit_failure();
+    loop {}
+}
@@ -0,0 +1,142 @@
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![test_runner(panda::test_runner)]
+
+use bootloader::{entry_point, BootInfo};
+use panda::interrupts::irq::{self, irq_vector};
+use panda::pci::{
+    self,
+    msi::{self, MsiKind},
+    PciCapabilityId, PciDeviceAddress, PciDeviceRegister, SLOTS_PER_BUS,
+};
+use panda::*;
+
+const COMMAND_INTERRUPT_DISABLE: u16 = 1 << 10;
+
+// Offsets and bits in the MSI and MSI-X capabilities.
+const CONTROL: u8 = 0x02;
+const MSI_ADDRESS: u8 = 0x04;
+const MSI_ENABLE: u16 = 1 << 0;
+const MSI_64_BIT: u16 = 1 << 7;
+const MSIX_ENABLE: u16 = 1 << 15;
+
+entry_point!(main);
+
+fn main(boot_info: &'static BootInfo) -> ! {
+    serial_print!("testing MSI and MSI-X interrupt allocation... ");
+
+    gdt::init();
+    interrupts::init();
+    pic::init();
+    memory::init(boot_info);
+    gdt::init_stacks();
+    device::init();
+    acpi::init();
+    apic::init();
+    pci::init();
+
+    // On q35 the AHCI controller has MSI and virtio-vga MSI-X.
+    let msi_device = find_device(|address| {
+        address.find_capability(PciCapabilityId::Msi).is_some()
+            && address.find_capability(PciCapabilityId::MsiX).is_none()
+    })
+    .expect("no device with only MSI");
+    let msix_device =
+        find_device(|address| address.find_capability(PciCapabilityId::MsiX).is_some())
+            .expect("no device with MSI-X");
+
+    test_msi(msi_device);
+    test_msix(msix_device);
+
+    serial_println!("[ok]");
+
+    qemu::exit_success();
+    loop {}
+}
+
+fn test_msi(address: PciDeviceAddress) {
+    let capability = address.find_capability(PciCapabilityId::Msi).unwrap();
+    let command = address.read::<u16>(PciDeviceRegister::Command);
+
+    let interrupts = msi::allocate_interrupts(address, 1).expect("MSI allocation failed");
+    let first_irq = interrupts.irq(0);
+
+    assert_eq!(interrupts.kind(), MsiKind::Msi);
+    assert_eq!(interrupts.irqs(), first_irq..first_irq + 1);
+    assert_ne!(
+        address.read::<u16>(PciDeviceRegister::Command) & COMMAND_INTERRUPT_DISABLE,
+        0
+    );
+
+    // Delivered to this CPU's local APIC, on the IRQ's vector.
+    let control = capability.read::<u16>(CONTROL);
+    let data_offset = if control & MSI_64_BIT != 0 {
+        0x0C
+    } else {
+        0x08
+    };
+    let local_apic_id = apic::local_apic().unwrap().id() as u32;
+    assert_ne!(control & MSI_ENABLE, 0);
+    assert_eq!(
+        capability.read::<u32>(MSI_ADDRESS),
+        0xFEE0_0000 | (local_apic_id << 12)
+    );
+    assert_eq!(
+        capability.read::<u16>(data_offset),
+        irq_vector(first_irq) as u16
+    );
+
+    drop(interrupts);
+
+    assert_eq!(capability.read::<u16>(CONTROL) & MSI_ENABLE, 0);
+    assert_eq!(address.read::<u16>(PciDeviceRegister::Command), command);
+
+    // The IRQ went back to the pool.
+    assert_eq!(irq::allocate_irqs(1, true), Some(first_irq));
+    irq::free_irqs(first_irq, 1);
+}
+
+fn test_msix(address: PciDeviceAddress) {
+    let capability = address.find_capability(PciCapabilityId::MsiX).unwrap();
+    let command = address.read::<u16>(PciDeviceRegister::Command);
+
+    let interrupts = msi::allocate_interrupts(address, 2).expect("MSI-X allocation failed");
+    let irqs = interrupts.irqs();
+
+    assert_eq!(interrupts.kind(), MsiKind::MsiX);
+    assert!((1..=2).contains(&irqs.len()));
+    assert_ne!(capability.read::<u16>(CONTROL) & MSIX_ENABLE, 0);
+    assert_ne!(
+        address.read::<u16>(PciDeviceRegister::Command) & COMMAND_INTERRUPT_DISABLE,
+        0
+    );
+
+    drop(interrupts);
+
+    assert_eq!(capability.read::<u16>(CONTROL) & MSIX_ENABLE, 0);
+    assert_eq!(address.read::<u16>(PciDeviceRegister::Command), command);
+
+    let count = irqs.len() as u8;
+    assert_eq!(irq::allocate_irqs(count, false), Some(irqs.start));
+    irq::free_irqs(irqs.start, count);
+}
+
+fn find_device(matches: impl Fn(&PciDeviceAddress) -> bool) -> Option<PciDeviceAddress> {
+    let base_address = pci::base_address_for_segment(0).expect("no PCI configuration space");
+
+    (0..SLOTS_PER_BUS)
+        .flat_map(|slot| {
+            (0..8).map(move |function| PciDeviceAddress::new(base_address, 0, 0, slot, function))
+        })
+        .filter(|address| address.is_valid_device())
+        .find(matches)
+}
+
+#[panic_handler]
+fn panic(info: &core::panic::PanicInfo) -> ! {
+    serial_println!("[failed]");
+    serial_println!("{}", info);
+    qemu::exit_failure();
+    loop {}
+}
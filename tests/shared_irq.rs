@@ -0,0 +1,119 @@
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![test_runner(panda::test_runner)]
+
+extern crate alloc;
+
+use alloc::boxed::Box;
+use core::{
+    future::Future,
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+    task::Context,
+};
+
+use bootloader::{entry_point, BootInfo};
+use futures_util::task::noop_waker_ref;
+use panda::interrupts::irq::{self, irq_vector, IrqRegistration, IrqResult};
+use panda::*;
+
+static BYSTANDER_CALLS: AtomicUsize = AtomicUsize::new(0);
+static OWNER_CALLS: AtomicUsize = AtomicUsize::new(0);
+static OWNER_CLAIMS: AtomicBool = AtomicBool::new(false);
+
+entry_point!(main);
+
+fn main(boot_info: &'static BootInfo) -> ! {
+    serial_print!("testing shared IRQ dispatch... ");
+
+    gdt::init();
+    interrupts::init();
+    pic::init();
+    memory::init(boot_info);
+    gdt::init_stacks();
+    device::init();
+    acpi::init();
+    apic::init();
+    x86_64::instructions::interrupts::enable();
+
+    let irq = irq::allocate_irqs(1, false).expect("no free IRQ");
+
+    let bystander = irq::register_handler(irq, |_| {
+        BYSTANDER_CALLS.fetch_add(1, Ordering::Relaxed);
+        IrqResult::NotMine
+    });
+    let owner = irq::register_handler(irq, |_| {
+        OWNER_CALLS.fetch_add(1, Ordering::Relaxed);
+        if OWNER_CLAIMS.load(Ordering::Relaxed) {
+            IrqResult::Handled
+        } else {
+            IrqResult::NotMine
+        }
+    });
+    let listener = irq::register(irq);
+    assert_eq!(irq::subscriber_count(irq), 3);
+
+    // Every handler on the line is asked, but only registrations whose
+    // handler claims the interrupt are woken. One without a handler always
+    // is.
+    raise(irq);
+    assert_eq!(BYSTANDER_CALLS.load(Ordering::Relaxed), 1);
+    assert_eq!(OWNER_CALLS.load(Ordering::Relaxed), 1);
+    assert!(!is_woken(&bystander));
+    assert!(!is_woken(&owner));
+    assert!(is_woken(&listener));
+
+    OWNER_CLAIMS.store(true, Ordering::Relaxed);
+    raise(irq);
+    assert_eq!(BYSTANDER_CALLS.load(Ordering::Relaxed), 2);
+    assert_eq!(OWNER_CALLS.load(Ordering::Relaxed), 2);
+    assert!(!is_woken(&bystander));
+    assert!(is_woken(&owner));
+    assert!(is_woken(&listener));
+
+    // A dropped registration's handler is no longer run.
+    drop(owner);
+    assert_eq!(irq::subscriber_count(irq), 2);
+    raise(irq);
+    assert_eq!(BYSTANDER_CALLS.load(Ordering::Relaxed), 3);
+    assert_eq!(OWNER_CALLS.load(Ordering::Relaxed), 2);
+    assert!(is_woken(&listener));
+
+    drop(bystander);
+    drop(listener);
+    assert_eq!(irq::subscriber_count(irq), 0);
+    irq::free_irqs(irq, 1);
+
+    serial_println!("[ok]");
+
+    qemu::exit_success();
+    loop {}
+}
+
+/// Sends the IRQ's vector to this CPU and waits for it to be handled.
+fn raise(irq: u8) {
+    let count = irq::IRQ_COUNT[irq as usize].load(Ordering::Relaxed);
+
+    let local_apic = apic::local_apic().expect("no local APIC");
+    local_apic.send_ipi(local_apic.id(), irq_vector(irq) as u32);
+
+    while irq::IRQ_COUNT[irq as usize].load(Ordering::Relaxed) == count {
+        core::sync::atomic::spin_loop_hint();
+    }
+}
+
+fn is_woken(registration: &IrqRegistration) -> bool {
+    let mut context = Context::from_waker(noop_waker_ref());
+    Box::pin(registration.wait())
+        .as_mut()
+        .poll(&mut context)
+        .is_ready()
+}
+
+#[panic_handler]
+fn panic(info: &core::panic::PanicInfo) -> ! {
+    serial_println!("[failed]");
+    serial_println!("{}", info);
+    qemu::exit_failure();
+    loop {}
+}
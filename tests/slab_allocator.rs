@@ -0,0 +1,79 @@
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![test_runner(panda::test_runner)]
+
+extern crate alloc;
+
+use alloc::{boxed::Box, vec::Vec};
+use bootloader::{entry_point, BootInfo};
+use panda::memory::{self, slab::SlabStats};
+use panda::*;
+
+// Fills several slabs of the 64-byte size class.
+const OBJECTS: usize = 500;
+const OBJECTS_PER_PAGE: usize = 4096 / 64;
+
+entry_point!(main);
+
+fn main(boot_info: &'static BootInfo) -> ! {
+    serial_print!("testing slab refill and reuse... ");
+
+    gdt::init();
+    interrupts::init();
+    memory::init(boot_info);
+
+    // Allocated up front from a different size class, so it doesn't count
+    // against the one being tested.
+    let mut objects: Vec<Box<[u64; 8]>> = Vec::with_capacity(OBJECTS);
+
+    let before = stats();
+    let heap_used = memory::heap_stats().used;
+
+    for index in 0..OBJECTS {
+        objects.push(Box::new([index as u64; 8]));
+    }
+
+    let filled = stats();
+    assert_eq!(filled.objects_in_use, before.objects_in_use + OBJECTS);
+    assert!(filled.pages * OBJECTS_PER_PAGE >= filled.objects_in_use);
+    assert!(filled.pages >= before.pages + OBJECTS / OBJECTS_PER_PAGE);
+    // None of it came from the linked-list heap.
+    assert_eq!(memory::heap_stats().used, heap_used);
+
+    // Every object is aligned to its size class and kept its contents while
+    // the slabs around it were refilled.
+    for (index, object) in objects.iter().enumerate() {
+        assert_eq!(&**object as *const _ as usize % 64, 0);
+        assert!(object.iter().all(|&value| value == index as u64));
+    }
+
+    objects.clear();
+    assert_eq!(stats().objects_in_use, before.objects_in_use);
+
+    // Freed objects are handed out again before any new slab is carved.
+    for index in 0..OBJECTS {
+        objects.push(Box::new([index as u64; 8]));
+    }
+    assert_eq!(stats().pages, filled.pages);
+    objects.clear();
+
+    serial_println!("[ok]");
+
+    qemu::exit_success();
+    loop {}
+}
+
+fn stats() -> SlabStats {
+    memory::slab_stats()
+        .find(|stats| stats.object_size == 64)
+        .expect("no 64-byte slab cache")
+}
+
+#[panic_handler]
+fn panic(info: &core::panic::PanicInfo) -> ! {
+    serial_println!("[failed]");
+    serial_println!("{}", info);
+    qemu::exit_failure();
+    loop {}
+}
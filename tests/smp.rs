@@ -0,0 +1,104 @@
+#![no_std]
+#![no_main]
+#![feature(const_in_array_repeat_expressions)]
+#![feature(custom_test_frameworks)]
+#![test_runner(panda::test_runner)]
+
+extern crate alloc;
+
+use core::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+
+use bootloader::{entry_point, BootInfo};
+use panda::task::{Priority, Task};
+use panda::*;
+
+// Matches `-smp 4` in the test arguments.
+const EXPECTED_CPUS: usize = 4;
+const TASKS: usize = 200;
+const READS_PER_TASK: usize = 50_000;
+
+// Read over and over, so a CPU running with its caches disabled is many
+// times slower than the rest.
+static BUFFER: [u64; 4096] = [1; 4096];
+
+static CPUS_USED: AtomicU64 = AtomicU64::new(0);
+static COMPLETED: AtomicUsize = AtomicUsize::new(0);
+static TASKS_RUN: [AtomicU64; EXPECTED_CPUS] = [AtomicU64::new(0); EXPECTED_CPUS];
+static BUSY_NANOSECONDS: [AtomicU64; EXPECTED_CPUS] = [AtomicU64::new(0); EXPECTED_CPUS];
+
+entry_point!(main);
+
+fn main(boot_info: &'static BootInfo) -> ! {
+    serial_print!("testing that tasks run on every CPU... ");
+
+    gdt::init();
+    interrupts::init();
+    pic::init();
+    memory::init(boot_info);
+    gdt::init_stacks();
+    device::init();
+    acpi::init();
+    apic::init();
+    time::init(time::DEFAULT_TIMER_FREQUENCY);
+    thread::init();
+    smp::init();
+
+    assert_eq!(smp::cpu_count(), EXPECTED_CPUS);
+
+    let mut executor = task::init();
+    for _ in 0..TASKS {
+        // Busy for long enough that idle CPUs steal from the boot
+        // processor's queue before it gets through them all.
+        executor.spawn(Task::new("busy", Priority::Background, async {
+            let cpu = smp::current_cpu();
+            assert!(cpu < EXPECTED_CPUS);
+
+            let start = time::now();
+            let mut sum = 0u64;
+            for index in 0..READS_PER_TASK {
+                let value = unsafe { core::ptr::read_volatile(&BUFFER[index % BUFFER.len()]) };
+                sum = sum.wrapping_add(value);
+            }
+            assert_eq!(sum, READS_PER_TASK as u64);
+
+            BUSY_NANOSECONDS[cpu].fetch_add(start.elapsed().as_nanos() as u64, Ordering::Relaxed);
+            TASKS_RUN[cpu].fetch_add(1, Ordering::Relaxed);
+            CPUS_USED.fetch_or(1 << cpu, Ordering::Relaxed);
+            COMPLETED.fetch_add(1, Ordering::Relaxed);
+        }));
+    }
+
+    executor.run_to_completion();
+
+    assert_eq!(COMPLETED.load(Ordering::Relaxed), TASKS);
+    assert_eq!(
+        CPUS_USED.load(Ordering::Relaxed).count_ones() as usize,
+        EXPECTED_CPUS
+    );
+
+    // The same work should take about as long wherever it runs.
+    let average_nanoseconds = |cpu: usize| {
+        BUSY_NANOSECONDS[cpu].load(Ordering::Relaxed) / TASKS_RUN[cpu].load(Ordering::Relaxed)
+    };
+    let fastest = (0..EXPECTED_CPUS).map(average_nanoseconds).min().unwrap();
+    let slowest = (0..EXPECTED_CPUS).map(average_nanoseconds).max().unwrap();
+    assert!(
+        slowest <= fastest * 4,
+        "tasks took {} ns on the slowest CPU and {} ns on the fastest",
+        slowest,
+        fastest
+    );
+
+    serial_println!("[ok]");
+
+    qemu::exit_success();
+    loop {}
+}
+
+#[panic_handler]
+fn panic(info: &core::panic::PanicInfo) -> ! {
+    serial_println!("[failed]");
+    serial_println!("{}", info);
+    qemu::exit_failure();
+    loop {}
+}
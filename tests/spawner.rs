@@ -0,0 +1,94 @@
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![test_runner(panda::test_runner)]
+
+extern crate alloc;
+
+use core::{
+    future::Future,
+    pin::Pin,
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+    task::{Context, Poll, Waker},
+};
+
+use bootloader::{entry_point, BootInfo};
+use panda::task::{self, Executor, Priority, Task};
+use panda::*;
+use spin::Mutex;
+
+static STUCK_POLLS: AtomicUsize = AtomicUsize::new(0);
+static STUCK_DROPPED: AtomicBool = AtomicBool::new(false);
+static STUCK_WAKER: Mutex<Option<Waker>> = Mutex::new(None);
+static PARENT_DONE: AtomicBool = AtomicBool::new(false);
+
+/// Never completes; keeps its waker so it can be woken after being
+/// cancelled.
+struct Stuck;
+
+impl Future for Stuck {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        STUCK_POLLS.fetch_add(1, Ordering::Relaxed);
+        *STUCK_WAKER.lock() = Some(cx.waker().clone());
+        Poll::Pending
+    }
+}
+
+impl Drop for Stuck {
+    fn drop(&mut self) {
+        STUCK_DROPPED.store(true, Ordering::Relaxed);
+    }
+}
+
+entry_point!(main);
+
+fn main(boot_info: &'static BootInfo) -> ! {
+    serial_print!("testing spawning from tasks and join handles... ");
+
+    gdt::init();
+    interrupts::init();
+    memory::init(boot_info);
+
+    let mut executor = Executor::new();
+    let spawner = executor.spawner();
+
+    executor.spawn(Task::new("parent", Priority::Background, async move {
+        let child = spawner.spawn("child", Priority::Interactive, async { 6 * 7 });
+        assert_eq!(child.await, Ok(42));
+
+        let stuck = spawner.spawn("stuck", Priority::Background, Stuck);
+        while STUCK_POLLS.load(Ordering::Relaxed) == 0 {
+            task::yield_now().await;
+        }
+
+        drop(stuck);
+        let waker = STUCK_WAKER.lock().take().expect("stuck task has no waker");
+        waker.wake();
+
+        PARENT_DONE.store(true, Ordering::Relaxed);
+    }));
+
+    executor.run_to_completion();
+
+    assert!(PARENT_DONE.load(Ordering::Relaxed));
+    // Cancelled by dropping its handle, so it was dropped without being
+    // polled again despite the wakeup.
+    assert_eq!(STUCK_POLLS.load(Ordering::Relaxed), 1);
+    assert!(STUCK_DROPPED.load(Ordering::Relaxed));
+    assert!(task::tasks().is_empty());
+
+    serial_println!("[ok]");
+
+    qemu::exit_success();
+    loop {}
+}
+
+#[panic_handler]
+fn panic(info: &core::panic::PanicInfo) -> ! {
+    serial_println!("[failed]");
+    serial_println!("{}", info);
+    qemu::exit_failure();
+    loop {}
+}
@@ -0,0 +1,154 @@
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![test_runner(panda::test_runner)]
+
+extern crate alloc;
+
+use alloc::{boxed::Box, sync::Arc, vec::Vec};
+use core::{future::Future, task::Context};
+
+use bootloader::{entry_point, BootInfo};
+use futures_util::task::noop_waker_ref;
+use panda::sync::{mpsc, oneshot, Mutex, Notify, RwLock, Semaphore};
+use panda::task::{self, Executor, Priority, Task};
+use panda::*;
+
+const WORKERS: usize = 20;
+const MESSAGES: usize = 100;
+
+entry_point!(main);
+
+fn main(boot_info: &'static BootInfo) -> ! {
+    serial_print!("testing async locks, channels and notifications... ");
+
+    panda::gdt::init();
+    panda::interrupts::init();
+    panda::memory::init(boot_info);
+
+    // A waiter woken by `notify_waiters` and dropped before seeing it
+    // mustn't leave a permit behind for the next one.
+    let notify_all = Notify::new();
+    let mut context = Context::from_waker(noop_waker_ref());
+    {
+        let mut waiter = Box::pin(notify_all.notified());
+        assert!(waiter.as_mut().poll(&mut context).is_pending());
+        notify_all.notify_waiters();
+    }
+    let mut waiter = Box::pin(notify_all.notified());
+    assert!(waiter.as_mut().poll(&mut context).is_pending());
+
+    let mut executor = Executor::new();
+
+    // Every worker holds the lock across a yield; the increments must not
+    // interleave.
+    let counter = Arc::new(Mutex::new(0));
+    for _ in 0..WORKERS {
+        let counter = counter.clone();
+        executor.spawn(Task::new("mutex", Priority::Interactive, async move {
+            let mut value = counter.lock().await;
+            let seen = *value;
+            task::yield_now().await;
+            *value = seen + 1;
+        }));
+    }
+
+    let lock = Arc::new(RwLock::new(Vec::new()));
+    for index in 0..WORKERS {
+        let lock = lock.clone();
+        executor.spawn(Task::new("rwlock", Priority::Interactive, async move {
+            if index % 2 == 0 {
+                lock.write().await.push(index);
+            } else {
+                let readers = lock.read().await;
+                task::yield_now().await;
+                assert!(readers.len() <= WORKERS);
+            }
+        }));
+    }
+
+    let semaphore = Arc::new(Semaphore::new(3));
+    for _ in 0..WORKERS {
+        let semaphore = semaphore.clone();
+        executor.spawn(Task::new("semaphore", Priority::Interactive, async move {
+            let _permit = semaphore.acquire().await.unwrap();
+            assert!(semaphore.available_permits() < 3);
+            task::yield_now().await;
+        }));
+    }
+
+    // A small buffer makes the producer wait on the consumer.
+    let (sender, mut receiver) = mpsc::channel(2);
+    let (reply_sender, reply_receiver) = oneshot::channel();
+    executor.spawn(Task::new("producer", Priority::Interactive, async move {
+        for message in 0..MESSAGES {
+            sender.send(message).await.unwrap();
+        }
+    }));
+    executor.spawn(Task::new("consumer", Priority::Interactive, async move {
+        let mut received = Vec::new();
+        while let Some(message) = receiver.recv().await {
+            received.push(message);
+        }
+        reply_sender.send(received).unwrap();
+    }));
+
+    // Buffered values are still received after closing, then the channel
+    // ends even though the sender is still alive.
+    executor.spawn(Task::new("close", Priority::Interactive, async move {
+        let (sender, mut receiver) = mpsc::channel(4);
+        sender.try_send(1).unwrap();
+        receiver.close();
+
+        assert!(matches!(
+            sender.try_send(2),
+            Err(mpsc::TrySendError::Closed(2))
+        ));
+        assert_eq!(receiver.recv().await, Some(1));
+        assert_eq!(receiver.recv().await, None);
+    }));
+
+    let notify = Arc::new(Notify::new());
+    let notified = Arc::new(Mutex::new(false));
+    {
+        let notify = notify.clone();
+        let notified = notified.clone();
+        executor.spawn(Task::new("notified", Priority::Interactive, async move {
+            notify.notified().await;
+            *notified.lock().await = true;
+        }));
+    }
+
+    let result = Arc::new(Mutex::new(None));
+    {
+        let result = result.clone();
+        executor.spawn(Task::new("reply", Priority::Interactive, async move {
+            *result.lock().await = Some(reply_receiver.await.unwrap());
+            notify.notify_one();
+        }));
+    }
+
+    executor.run_to_completion();
+
+    assert_eq!(counter.try_lock().map(|value| *value), Some(WORKERS));
+    assert_eq!(lock.try_read().unwrap().len(), WORKERS / 2);
+    assert_eq!(semaphore.available_permits(), 3);
+    assert_eq!(
+        result.try_lock().unwrap().take(),
+        Some((0..MESSAGES).collect::<Vec<_>>())
+    );
+    assert_eq!(notified.try_lock().map(|value| *value), Some(true));
+
+    serial_println!("[ok]");
+
+    qemu::exit_success();
+    loop {}
+}
+
+#[panic_handler]
+fn panic(info: &core::panic::PanicInfo) -> ! {
+    serial_println!("[failed]");
+    serial_println!("{}", info);
+    qemu::exit_failure();
+    loop {}
+}
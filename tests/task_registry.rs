@@ -0,0 +1,116 @@
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![test_runner(panda::test_runner)]
+
+extern crate alloc;
+
+use core::{
+    future::Future,
+    pin::Pin,
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+    task::{Context, Poll},
+};
+
+use bootloader::{entry_point, BootInfo};
+use panda::task::{self, Executor, Priority, Task, TaskId, TaskState, WaitReason};
+use panda::*;
+use spin::Mutex;
+
+static VICTIM_POLLS: AtomicUsize = AtomicUsize::new(0);
+static VICTIM_DROPPED: AtomicBool = AtomicBool::new(false);
+static VICTIM_SAW: Mutex<Option<TaskId>> = Mutex::new(None);
+static WATCHER_DONE: AtomicBool = AtomicBool::new(false);
+
+/// Never completes or wakes itself, so only aborting it gets rid of it.
+struct Victim;
+
+impl Future for Victim {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<()> {
+        VICTIM_POLLS.fetch_add(1, Ordering::Relaxed);
+        *VICTIM_SAW.lock() = task::current_task();
+        Poll::Pending
+    }
+}
+
+impl Drop for Victim {
+    fn drop(&mut self) {
+        VICTIM_DROPPED.store(true, Ordering::Relaxed);
+    }
+}
+
+entry_point!(main);
+
+fn main(boot_info: &'static BootInfo) -> ! {
+    serial_print!("testing the task registry and aborting a pending task... ");
+
+    gdt::init();
+    interrupts::init();
+    memory::init(boot_info);
+
+    let mut executor = Executor::new();
+
+    let victim = Task::new(
+        "victim",
+        Priority::Background,
+        task::waiting_on(WaitReason::Other("test"), Victim),
+    );
+    let victim_id = victim.id;
+    executor.spawn(victim);
+
+    let watcher = Task::new("watcher", Priority::Background, async move {
+        while VICTIM_POLLS.load(Ordering::Relaxed) == 0 {
+            task::yield_now().await;
+        }
+
+        let tasks = task::tasks();
+        let info = tasks
+            .iter()
+            .find(|info| info.id == victim_id)
+            .expect("victim isn't registered");
+        assert_eq!(info.name, "victim");
+        assert_eq!(info.state, TaskState::Waiting);
+        assert_eq!(info.polls, 1);
+        assert_eq!(info.wait_reason, Some(WaitReason::Other("test")));
+
+        let current = task::current_task().expect("no current task");
+        assert_ne!(current, victim_id);
+        assert!(tasks.iter().any(|info| info.id == current
+            && info.name == "watcher"
+            && info.state == TaskState::Running));
+
+        task::dump();
+
+        assert!(task::abort(victim_id));
+        task::yield_now().await;
+
+        WATCHER_DONE.store(true, Ordering::Relaxed);
+    });
+    executor.spawn(watcher);
+
+    executor.run_to_completion();
+
+    assert!(WATCHER_DONE.load(Ordering::Relaxed));
+    assert_eq!(*VICTIM_SAW.lock(), Some(victim_id));
+    // Dropped without being polled again, and gone from the registry.
+    assert_eq!(VICTIM_POLLS.load(Ordering::Relaxed), 1);
+    assert!(VICTIM_DROPPED.load(Ordering::Relaxed));
+    assert!(task::tasks().is_empty());
+    assert!(!task::abort(victim_id));
+    assert_eq!(task::current_task(), None);
+
+    serial_println!("[ok]");
+
+    qemu::exit_success();
+    loop {}
+}
+
+#[panic_handler]
+fn panic(info: &core::panic::PanicInfo) -> ! {
+    serial_println!("[failed]");
+    serial_println!("{}", info);
+    qemu::exit_failure();
+    loop {}
+}
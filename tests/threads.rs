@@ -0,0 +1,91 @@
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![test_runner(panda::test_runner)]
+
+extern crate alloc;
+
+use alloc::{sync::Arc, vec::Vec};
+use core::{
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+    time::Duration,
+};
+
+use bootloader::{entry_point, BootInfo};
+use panda::*;
+
+static SPINNING: [AtomicUsize; 2] = [AtomicUsize::new(0), AtomicUsize::new(0)];
+static STOP: AtomicBool = AtomicBool::new(false);
+
+entry_point!(main);
+
+fn main(boot_info: &'static BootInfo) -> ! {
+    serial_print!("testing that busy kernel threads are preempted... ");
+
+    gdt::init();
+    interrupts::init();
+    pic::init();
+    memory::init(boot_info);
+    gdt::init_stacks();
+    device::init();
+    acpi::init();
+    apic::init();
+    time::init(time::DEFAULT_TIMER_FREQUENCY);
+    thread::init();
+
+    // Neither thread ever yields, so they only share the CPU with this one
+    // if the timer preempts them.
+    let spinners = (0..SPINNING.len())
+        .map(|index| {
+            thread::spawn("spinner", move || {
+                while !STOP.load(Ordering::Relaxed) {
+                    SPINNING[index].fetch_add(1, Ordering::Relaxed);
+                }
+            })
+        })
+        .collect::<Vec<_>>();
+
+    thread::sleep(Duration::from_millis(100));
+    assert!(SPINNING
+        .iter()
+        .all(|count| count.load(Ordering::Relaxed) > 0));
+
+    STOP.store(true, Ordering::Relaxed);
+    for spinner in spinners {
+        spinner.join();
+    }
+
+    // Threads can block on the async primitives and hand back results.
+    let lock = Arc::new(sync::Mutex::new(0));
+    let workers = (0..4)
+        .map(|_| {
+            let lock = lock.clone();
+            thread::spawn("worker", move || {
+                for _ in 0..100 {
+                    *thread::block_on(lock.lock()) += 1;
+                    thread::yield_now();
+                }
+                thread::current()
+            })
+        })
+        .collect::<Vec<_>>();
+
+    for worker in workers {
+        let id = worker.id();
+        assert_eq!(worker.join(), id);
+    }
+    assert_eq!(*lock.try_lock().unwrap(), 400);
+
+    serial_println!("[ok]");
+
+    qemu::exit_success();
+    loop {}
+}
+
+#[panic_handler]
+fn panic(info: &core::panic::PanicInfo) -> ! {
+    serial_println!("[failed]");
+    serial_println!("{}", info);
+    qemu::exit_failure();
+    loop {}
+}
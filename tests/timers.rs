@@ -0,0 +1,70 @@
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![test_runner(panda::test_runner)]
+
+extern crate alloc;
+
+use core::{
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Duration,
+};
+
+use bootloader::{entry_point, BootInfo};
+use panda::task::{Executor, Priority, Task};
+use panda::time::timer::pending_timers;
+use panda::*;
+
+const ITERATIONS: usize = 20;
+
+static COMPLETED: AtomicUsize = AtomicUsize::new(0);
+
+entry_point!(main);
+
+fn main(boot_info: &'static BootInfo) -> ! {
+    serial_print!("testing that cancelled timers are removed... ");
+
+    gdt::init();
+    interrupts::init();
+    pic::init();
+    memory::init(boot_info);
+    gdt::init_stacks();
+    device::init();
+    acpi::init();
+    apic::init();
+    time::init(time::DEFAULT_TIMER_FREQUENCY);
+
+    let mut executor = Executor::new();
+    executor.spawn(Task::new("timeouts", Priority::Background, async {
+        for _ in 0..ITERATIONS {
+            // The sleep wins, so the timeout's own timer is cancelled.
+            let result = time::timeout(
+                time::sleep(Duration::from_millis(1)),
+                Duration::from_secs(10),
+            )
+            .await;
+
+            assert!(result.is_ok());
+            assert_eq!(pending_timers(), 0);
+            COMPLETED.fetch_add(1, Ordering::Relaxed);
+        }
+    }));
+
+    executor.run_to_completion();
+
+    assert_eq!(COMPLETED.load(Ordering::Relaxed), ITERATIONS);
+    assert_eq!(pending_timers(), 0);
+
+    serial_println!("[ok]");
+
+    qemu::exit_success();
+    loop {}
+}
+
+#[panic_handler]
+fn panic(info: &core::panic::PanicInfo) -> ! {
+    serial_println!("[failed]");
+    serial_println!("{}", info);
+    qemu::exit_failure();
+    loop {}
+}